@@ -0,0 +1,249 @@
+// Модуль для замера производительности обходов на сгенерированных графах
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::generate::{self, Params, Shape};
+use crate::graph::{DependencyGraph, Direction, EdgeInfo, Limits, TreeVisitor};
+use crate::scc;
+
+/// Размер графов по умолчанию
+pub const DEFAULT_NODES: usize = 100_000;
+
+// Графы для замеров: формы генератора тестовых репозиториев с подписью для таблицы
+fn cases(nodes: usize) -> Vec<(&'static str, Params)> {
+    let params = |shape, density, cycles| Params { nodes, density, cycles, ..Params::new(shape) };
+    vec![
+        // Одна длинная цепочка
+        ("chain", params(Shape::Chain, 0.0, 0)),
+        // Случайный ацикличный граф со средней степенью 3
+        ("dag", params(Shape::Random, 3.0, 0)),
+        // Тот же граф с циклами на каждую сотню пакетов (нетривиальные компоненты для SCC)
+        ("cyclic", params(Shape::Random, 3.0, nodes / 100)),
+        // Корень со всеми пакетами в зависимостях (отсев повторных рёбер у широких узлов); не больше
+        // 20 000 пакетов, так как прежнее представление отсеивает повторы за квадратичное время
+        ("star", Params { nodes: nodes.min(20_000), ..Params::new(Shape::Star) }),
+    ]
+}
+
+// Посетитель, который только считает узлы
+#[derive(Default)]
+struct Counter {
+    entered: usize,
+}
+
+impl TreeVisitor for Counter {
+    fn enter(&mut self, _name: &str, _depth: usize, _edge: Option<&EdgeInfo>) {
+        self.entered += 1;
+    }
+}
+
+// Распределитель памяти со счётчиком занятых байт кучи: нужен для замера размера графов.
+// Учитывается только запрошенный размер блоков, без служебных данных системного распределителя
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+// Прежнее представление графа для сравнения: имена хранятся ключами карты и в каждом
+// списке зависимостей, повторные рёбра отсеиваются через Vec::contains, а индекс
+// обратных зависимостей пересчитывается при каждом обратном обходе
+#[derive(Default)]
+struct StringGraph {
+    nodes: HashMap<String, StringNode>,
+}
+
+#[allow(dead_code)]
+#[derive(Default)]
+struct StringNode {
+    name: String,
+    version: Option<String>,
+    attributes: BTreeMap<String, String>,
+    dependencies: Vec<String>,
+}
+
+impl StringGraph {
+    fn ensure_node(&mut self, name: &str) {
+        self.nodes.entry(name.to_string()).or_insert_with(|| StringNode {
+            name: name.to_string(),
+            ..StringNode::default()
+        });
+    }
+
+    fn add_edge(&mut self, package: &str, depends_on: &str) {
+        self.ensure_node(package);
+        self.ensure_node(depends_on);
+        let deps = &mut self.nodes.get_mut(package).unwrap().dependencies;
+        if !deps.iter().any(|d| d == depends_on) {
+            deps.push(depends_on.to_string());
+        }
+    }
+
+    fn build_reverse_index(&self) -> HashMap<String, Vec<String>> {
+        let mut rev: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, node) in &self.nodes {
+            for dep in &node.dependencies {
+                rev.entry(dep.clone()).or_default().push(name.clone());
+            }
+        }
+        rev
+    }
+}
+
+/// Команда `bench`: построение графов заданного размера, сравнение с прежним представлением
+/// на строках и замер обходов
+pub fn run(nodes: usize) {
+    let cases = cases(nodes);
+    if let Some(Err(e)) = cases.iter().map(|(_, params)| params.validate()).find(Result::is_err) {
+        eprintln!("BENCH ERROR: {}", e);
+        return;
+    }
+    let graphs: Vec<(&str, Vec<Vec<usize>>)> =
+        cases.iter().map(|(label, params)| (*label, generate::generate(params))).collect();
+
+    println!(
+        "{:<6} {:<9} {:>8} {:>8} {:>10} {:>10} {:>10}",
+        "shape", "layout", "nodes", "edges", "build", "memory", "rev-index"
+    );
+    for (label, deps) in &graphs {
+        let ((graph, build_time), memory) = measured(|| timed(|| build(deps)));
+        println!(
+            "{:<6} {:<9} {:>8} {:>8} {:>10} {:>10} {:>10}",
+            label,
+            "interned",
+            graph.len(),
+            graph.edge_count(),
+            ms(build_time),
+            mib(memory),
+            "-"
+        );
+        drop(graph);
+
+        let ((strings, build_time), memory) = measured(|| timed(|| build_strings(deps)));
+        let (_, rev_index) = timed(|| strings.build_reverse_index());
+        let edges: usize = strings.nodes.values().map(|n| n.dependencies.len()).sum();
+        println!(
+            "{:<6} {:<9} {:>8} {:>8} {:>10} {:>10} {:>10}",
+            "",
+            "strings",
+            strings.nodes.len(),
+            edges,
+            ms(build_time),
+            mib(memory),
+            ms(rev_index)
+        );
+    }
+
+    println!();
+    println!(
+        "{:<6} {:>10} {:>10} {:>10} {:>10}",
+        "shape", "dfs", "dfs-rev", "bfs", "scc"
+    );
+    for (label, deps) in &graphs {
+        let graph = build(deps);
+        let root = name(0);
+        let last = name(deps.len() - 1);
+
+        let (_, dfs) = timed(|| walk(&graph, &root, Direction::Forward));
+        let (_, dfs_rev) = timed(|| walk(&graph, &last, Direction::Reverse));
+        let (_, bfs) = timed(|| graph.bfs_levels(&root, "", Limits::default(), Direction::Forward));
+        let (_, scc) = timed(|| scc::strongly_connected_components(&graph));
+
+        println!(
+            "{:<6} {:>10} {:>10} {:>10} {:>10}",
+            label,
+            ms(dfs),
+            ms(dfs_rev),
+            ms(bfs),
+            ms(scc)
+        );
+    }
+}
+
+fn walk(graph: &DependencyGraph, root: &str, direction: Direction) -> usize {
+    let mut counter = Counter::default();
+    graph.walk(root, "", Limits::default(), direction, &mut counter);
+    counter.entered
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let value = f();
+    (value, start.elapsed())
+}
+
+// Прирост занятой кучи за время построения значения (значение остаётся живым)
+fn measured<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let value = f();
+    (value, ALLOCATED.load(Ordering::Relaxed).saturating_sub(before))
+}
+
+fn mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.1} ms", d.as_secs_f64() * 1000.0)
+}
+
+// Имя сгенерированного пакета; длина близка к типичным именам крейтов
+fn name(i: usize) -> String {
+    format!("package-{:06}", i)
+}
+
+// Построение графа из списков зависимостей генератора
+fn build(deps: &[Vec<usize>]) -> DependencyGraph {
+    let mut graph = DependencyGraph::new();
+    for i in 0..deps.len() {
+        graph.ensure_node(&name(i));
+    }
+    for (i, d) in deps.iter().enumerate() {
+        for &j in d {
+            graph.add_edge(&name(i), &name(j));
+        }
+    }
+    graph
+}
+
+// Построение графа в прежнем представлении на строках
+fn build_strings(deps: &[Vec<usize>]) -> StringGraph {
+    let mut graph = StringGraph::default();
+    for i in 0..deps.len() {
+        graph.ensure_node(&name(i));
+    }
+    for (i, d) in deps.iter().enumerate() {
+        for &j in d {
+            graph.add_edge(&name(i), &name(j));
+        }
+    }
+    graph
+}
@@ -6,8 +6,11 @@ use std::path::Path;
 use reqwest::blocking;
 use thiserror::Error;
 
+use crate::graph::{EdgeInfo, EdgeKind};
+
 
 /// Перечисление возможных ошибок при работе с Cargo.toml
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Error)]
 pub enum CargoParseError {
     #[error("failed to fetch Cargo.toml: {0}")]
@@ -20,87 +23,210 @@ pub enum CargoParseError {
     ParseError,
 }
 
+/// Прямая зависимость из Cargo.toml со сведениями о ребре
+#[derive(Debug, Clone)]
+pub struct ManifestDependency {
+    pub name: String,
+    pub info: EdgeInfo,
+}
+
 /// Извлекает список прямых зависимостей из Cargo.toml
-pub fn get_dependencies(repo_source: &str) -> Result<Vec<String>, CargoParseError> {
+pub fn get_dependencies(repo_source: &str) -> Result<Vec<ManifestDependency>, CargoParseError> {
     let content = if repo_source.starts_with("http") {
-        println!("Загрузка Cargo.toml из репозитория: {}", repo_source);
-
-        // Преобразование URL репозитория в ссылку на сырой Cargo.toml
-        // https://github.com/tokio-rs/tokio ->
-        // https://raw.githubusercontent.com/tokio-rs/tokio/master/Cargo.toml
-        let cargo_url = if repo_source.ends_with(".git") {
-            format!("{}/master/Cargo.toml", repo_source.trim_end_matches(".git"))
-        } else {
-            format!(
-                "https://raw.githubusercontent.com/{}/master/Cargo.toml",
-                repo_source
-                    .trim_start_matches("https://github.com/")
-                    .trim_end_matches('/')
-            )
-        };
-
-        blocking::get(&cargo_url)
-            .map_err(|e| CargoParseError::NetworkError(e.to_string()))?
-            .text()
-            .map_err(|e| CargoParseError::NetworkError(e.to_string()))?
-    } else if Path::new(repo_source).exists() {
-        println!("Чтение локального Cargo.toml: {}", repo_source);
-        fs::read_to_string(repo_source)
-            .map_err(|e| CargoParseError::FileError(e.to_string()))?
+        fetch_remote_manifest(repo_source)?
+    } else {
+        read_local_manifest(repo_source)?
+    };
+
+    parse_manifest_dependencies(&content)
+}
+
+/// Загрузка Cargo.toml из удалённого репозитория
+pub fn fetch_remote_manifest(repo_source: &str) -> Result<String, CargoParseError> {
+    println!("Загрузка Cargo.toml из репозитория: {}", repo_source);
+
+    // Преобразование URL репозитория в ссылку на сырой Cargo.toml
+    // https://github.com/tokio-rs/tokio ->
+    // https://raw.githubusercontent.com/tokio-rs/tokio/master/Cargo.toml
+    let cargo_url = if repo_source.ends_with(".git") {
+        format!("{}/master/Cargo.toml", repo_source.trim_end_matches(".git"))
     } else {
+        format!(
+            "https://raw.githubusercontent.com/{}/master/Cargo.toml",
+            repo_source
+                .trim_start_matches("https://github.com/")
+                .trim_end_matches('/')
+        )
+    };
+
+    blocking::get(&cargo_url)
+        .map_err(|e| CargoParseError::NetworkError(e.to_string()))?
+        .text()
+        .map_err(|e| CargoParseError::NetworkError(e.to_string()))
+}
+
+/// Чтение локального Cargo.toml (допускается путь к каталогу пакета)
+pub fn read_local_manifest(repo_source: &str) -> Result<String, CargoParseError> {
+    let path = Path::new(repo_source);
+    let path = if path.is_dir() { path.join("Cargo.toml") } else { path.to_path_buf() };
+
+    if !path.exists() {
         return Err(CargoParseError::FileError(format!(
             "Файл или URL не найден: {}",
             repo_source
         )));
-    };
+    }
 
-    // Извлечение секции [dependencies]
-    let deps_section = extract_dependencies_section(&content)?;
-    let deps = parse_dependencies(&deps_section);
-    Ok(deps)
+    println!("Чтение локального Cargo.toml: {}", path.display());
+    fs::read_to_string(&path).map_err(|e| CargoParseError::FileError(e.to_string()))
 }
 
-// Поиск и возврат секции [dependencies]
-fn extract_dependencies_section(cargo_toml: &str) -> Result<String, CargoParseError> {
-    let mut lines = Vec::new();
-    let mut in_deps = false;
-
-    for line in cargo_toml.lines() {
-        let trimmed = line.trim();
+/// Извлечение прямых зависимостей (обычных, dev и build) из текста Cargo.toml
+pub fn parse_manifest_dependencies(content: &str) -> Result<Vec<ManifestDependency>, CargoParseError> {
+    let mut deps: Vec<ManifestDependency> = Vec::new();
+    let mut section: Option<Section> = None;
+    let mut found = false;
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = strip_comment(line).trim();
+        if trimmed.is_empty() {
+            continue;
+        }
 
-        if trimmed.starts_with('[') && in_deps && trimmed != "[dependencies]" {
-            // выход из секции зависимостей
-            break;
+        // Заголовок секции: [dependencies], [dev-dependencies], [dependencies.serde], ...
+        if trimmed.starts_with('[') {
+            section = parse_header(trimmed, &mut deps);
+            found |= section.is_some();
+            continue;
         }
 
-        if in_deps {
-            lines.push(trimmed.to_string());
+        let Some(current) = section else { continue };
+        let Some((key, value)) = trimmed.split_once('=') else { continue };
+        let key = key.trim().trim_matches('"');
+        let mut value = value.trim().to_string();
+
+        // Многострочный массив (features = [ ... ])
+        if value.starts_with('[') && !value.contains(']') {
+            for next in lines.by_ref() {
+                value.push_str(strip_comment(next).trim());
+                if next.contains(']') {
+                    break;
+                }
+            }
         }
 
-        if trimmed == "[dependencies]" {
-            in_deps = true;
+        match current {
+            Section::List(kind) => {
+                let mut info = EdgeInfo { kind, ..EdgeInfo::default() };
+                if let Some(inner) = value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+                    for item in split_top_level(inner) {
+                        if let Some((k, v)) = item.split_once('=') {
+                            apply_key(k.trim(), v.trim(), &mut info);
+                        }
+                    }
+                }
+                if !key.is_empty() {
+                    deps.push(ManifestDependency { name: key.to_string(), info });
+                }
+            }
+            Section::Table(idx) => apply_key(key, &value, &mut deps[idx].info),
         }
     }
 
-    if lines.is_empty() {
+    if !found {
         return Err(CargoParseError::ParseError);
     }
 
-    Ok(lines.join("\n"))
+    Ok(deps)
+}
+
+// Текущая секция Cargo.toml
+#[derive(Clone, Copy)]
+enum Section {
+    /// Список зависимостей заданного типа
+    List(EdgeKind),
+    /// Табличная форма [dependencies.name]: индекс зависимости в результате
+    Table(usize),
+}
+
+// Разбор заголовка секции; None - секция не относится к зависимостям
+fn parse_header(header: &str, deps: &mut Vec<ManifestDependency>) -> Option<Section> {
+    if header.starts_with("[[") {
+        return None;
+    }
+    let inner = header.trim_start_matches('[').trim_end_matches(']').trim();
+
+    let keys = [
+        ("dev-dependencies", EdgeKind::Dev),
+        ("build-dependencies", EdgeKind::Build),
+        ("dependencies", EdgeKind::Normal),
+    ];
+    for (key, kind) in keys {
+        // [dependencies] или [target.'cfg(unix)'.dependencies]
+        if inner == key || inner.ends_with(&format!(".{key}")) {
+            return Some(Section::List(kind));
+        }
+        // [dependencies.serde] или [target.'cfg(unix)'.dependencies.serde]
+        let table = format!("{key}.");
+        if let Some(pos) = inner.find(&table)
+            && (pos == 0 || inner[..pos].ends_with('.'))
+        {
+            let name = inner[pos + table.len()..].trim_matches('"').to_string();
+            deps.push(ManifestDependency { name, info: EdgeInfo { kind, ..EdgeInfo::default() } });
+            return Some(Section::Table(deps.len() - 1));
+        }
+    }
+    None
 }
 
-/// Разбор зависимостей в формате `name = "version"`
-fn parse_dependencies(section: &str) -> Vec<String> {
-    let mut deps = Vec::new();
+// Применение ключа описания зависимости (optional, features)
+fn apply_key(key: &str, value: &str, info: &mut EdgeInfo) {
+    match key {
+        "optional" => info.optional = value == "true",
+        "features" => {
+            let inner = value.trim_start_matches('[').trim_end_matches(']');
+            info.features = split_top_level(inner)
+                .into_iter()
+                .map(|f| f.trim_matches('"').to_string())
+                .filter(|f| !f.is_empty())
+                .collect();
+        }
+        _ => {}
+    }
+}
 
-    for line in section.lines() {
-        if let Some((name, _)) = line.split_once('=') {
-            let dep_name = name.trim();
-            if !dep_name.is_empty() {
-                deps.push(dep_name.to_string());
+// Разделение по запятым верхнего уровня (вне кавычек и скобок)
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut in_str = false;
+    let mut start = 0;
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '"' => in_str = !in_str,
+            '[' | '{' if !in_str => depth += 1,
+            ']' | '}' if !in_str => depth -= 1,
+            ',' if !in_str && depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
             }
+            _ => {}
         }
     }
+    parts.push(s[start..].trim());
+    parts.into_iter().filter(|p| !p.is_empty()).collect()
+}
 
-    deps
+// Отбрасывание комментария (# вне строки)
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '"' => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
 }
@@ -0,0 +1,115 @@
+// Модуль для упорядочивания потомков узлов в деревьях и экспорте
+
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+use crate::graph::{DependencyGraph, Direction, NodeId};
+use crate::scc;
+
+/// Порядок потомков узла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChildOrder {
+    /// Порядок объявления зависимостей в источнике
+    #[default]
+    Declaration,
+    /// По имени пакета
+    Alpha,
+    /// Сначала потомки с большим числом транзитивных зависимостей
+    Size,
+    /// Сначала потомки с самой длинной цепочкой зависимостей под ними
+    Depth,
+}
+
+impl FromStr for ChildOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "declaration" => Ok(ChildOrder::Declaration),
+            "alpha" => Ok(ChildOrder::Alpha),
+            "size" => Ok(ChildOrder::Size),
+            "depth" => Ok(ChildOrder::Depth),
+            other => Err(format!("unknown child order '{other}', expected 'declaration', 'alpha', 'size' or 'depth'")),
+        }
+    }
+}
+
+/// Упорядочивание потомков в обоих направлениях обхода. Равные по размеру или глубине
+/// потомки идут по имени, поэтому результат не зависит от порядка добавления рёбер
+pub fn apply(graph: &mut DependencyGraph, order: ChildOrder) {
+    if order == ChildOrder::Declaration {
+        return;
+    }
+    let names: Vec<String> = (0..graph.len()).map(|i| graph.name(id(i)).to_string()).collect();
+    for direction in [Direction::Forward, Direction::Reverse] {
+        let weight = match order {
+            ChildOrder::Size => subtree_sizes(&adjacency(graph, direction)),
+            ChildOrder::Depth => heights(&adjacency(graph, direction)),
+            _ => vec![0; graph.len()],
+        };
+        graph.sort_children(direction, |n| (Reverse(weight[n.index()]), names[n.index()].clone()));
+    }
+}
+
+fn id(i: usize) -> NodeId {
+    NodeId::from_index(i)
+}
+
+// Списки смежности по идентификаторам в заданном направлении
+fn adjacency(graph: &DependencyGraph, direction: Direction) -> Vec<Vec<usize>> {
+    (0..graph.len())
+        .map(|i| {
+            let next = match direction {
+                Direction::Forward => graph.successors(id(i)),
+                Direction::Reverse => graph.predecessors(id(i)),
+            };
+            next.iter().map(|n| n.index()).collect()
+        })
+        .collect()
+}
+
+// Число узлов, достижимых из каждого узла (без него самого)
+fn subtree_sizes(adj: &[Vec<usize>]) -> Vec<usize> {
+    let mut seen = vec![usize::MAX; adj.len()];
+    (0..adj.len())
+        .map(|start| {
+            seen[start] = start;
+            let mut stack = vec![start];
+            let mut count = 0;
+            while let Some(v) = stack.pop() {
+                for &w in &adj[v] {
+                    if seen[w] != start {
+                        seen[w] = start;
+                        count += 1;
+                        stack.push(w);
+                    }
+                }
+            }
+            count
+        })
+        .collect()
+}
+
+// Длина самой длинной цепочки под узлом в графе компонент (цикл считается одним звеном)
+fn heights(adj: &[Vec<usize>]) -> Vec<usize> {
+    let components = scc::tarjan(adj);
+    let mut component_of = vec![0; adj.len()];
+    for (c, members) in components.iter().enumerate() {
+        for &v in members {
+            component_of[v] = c;
+        }
+    }
+    // Тарьян выдаёт компоненты так, что достижимые из компоненты идут раньше неё
+    let mut height = vec![0; components.len()];
+    for (c, members) in components.iter().enumerate() {
+        height[c] = members
+            .iter()
+            .flat_map(|&v| &adj[v])
+            .map(|&w| component_of[w])
+            .filter(|&d| d != c)
+            .map(|d| height[d] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    (0..adj.len()).map(|v| height[component_of[v]]).collect()
+}
@@ -0,0 +1,321 @@
+// Модуль для разбора аргументов командной строки
+// Формат: <config.xml> [команда [аргументы]] [флаги] или [config.xml] generate|bench [аргументы] [флаги]
+
+use crate::config::{self, AppConfig, ConfigError};
+use crate::generate;
+use crate::output::OutputOverrides;
+use crate::transform::Transform;
+
+/// Команда приложения
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Вывод дерева зависимостей (по умолчанию)
+    Tree,
+    /// Вывод компонент сильной связности и элементарных циклов
+    Cycles,
+    /// Порядок сборки и уровни параллельной сборки
+    Order,
+    /// Пути от корня до заданного пакета
+    Why(String),
+    /// Метрики узлов графа
+    Stats,
+    /// Сравнение с графом из другой конфигурации
+    Diff(String),
+    /// Дерево доминаторов от корня
+    Dominators,
+    /// Последствия удаления пакета из графа
+    Impact(String),
+    /// Запрос к графу на языке запросов
+    Query(String),
+    /// Замер обходов на сгенерированных графах заданного размера
+    Bench(Option<String>),
+    /// Генерация тестового репозитория заданной формы (в файл или на стандартный вывод)
+    Generate(String, Option<String>),
+}
+
+/// Разобранные аргументы командной строки
+#[derive(Debug)]
+pub struct CliArgs {
+    pub config_path: String,
+    pub command: Command,
+    pub outputs: OutputOverrides,
+    pub max_depth: Option<String>,
+    pub max_nodes: Option<String>,
+    /// Преобразования графа, включённые флагами --condense, --reduce, --collapse-chains
+    pub transforms: Vec<Transform>,
+    /// Максимальная длина перечисляемых циклов
+    pub max_cycle_len: Option<String>,
+    /// Вывод результата команды в формате JSON
+    pub json: bool,
+    /// Число кратчайших путей для команды why
+    pub shortest: Option<String>,
+    /// Столбец сортировки таблицы stats
+    pub sort: Option<String>,
+    /// Завершение с ненулевым кодом, если diff нашёл изменения
+    pub exit_code: bool,
+    /// Сохранение построенного графа в JSON-снимок
+    pub save_snapshot: Option<String>,
+    /// Загрузка графа из JSON-снимка вместо источника из конфига
+    pub load_snapshot: Option<String>,
+    /// Сохранение построенного графа в формате тестового репозитория
+    pub save_test_repo: Option<String>,
+    /// Замена имён пакетов на A, B, C, ... при сохранении тестового репозитория
+    pub anonymize: bool,
+    /// Вывод узлов по уровням обхода в ширину вместо дерева
+    pub bfs: bool,
+    /// Вывод повторно встреченных узлов (--dedupe <mode> или --no-dedupe)
+    pub dedupe: Option<String>,
+    /// Порядок потомков в деревьях и экспорте
+    pub child_order: Option<String>,
+    /// Вид текстового дерева
+    pub tree_style: Option<String>,
+    /// Номер глубины в начале строк дерева
+    pub depth_prefix: bool,
+    /// Раскраска текстового дерева
+    pub color: Option<String>,
+    /// Ширина, до которой обрезаются строки дерева
+    pub width: Option<String>,
+    /// Строгая проверка тестового репозитория
+    pub strict: bool,
+    /// Тема оформления D2
+    pub d2_theme: Option<String>,
+    /// Параметры команды generate
+    pub nodes: Option<String>,
+    pub density: Option<String>,
+    pub cycles: Option<String>,
+    pub components: Option<String>,
+    pub seed: Option<String>,
+}
+
+/// Разбор аргументов (без имени программы)
+pub fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut cli = CliArgs {
+        config_path: "config.example.xml".to_string(),
+        command: Command::Tree,
+        outputs: OutputOverrides::default(),
+        max_depth: None,
+        max_nodes: None,
+        transforms: Vec::new(),
+        max_cycle_len: None,
+        json: false,
+        shortest: None,
+        sort: None,
+        exit_code: false,
+        save_snapshot: None,
+        load_snapshot: None,
+        save_test_repo: None,
+        anonymize: false,
+        bfs: false,
+        dedupe: None,
+        child_order: None,
+        tree_style: None,
+        depth_prefix: false,
+        color: None,
+        width: None,
+        strict: false,
+        d2_theme: None,
+        nodes: None,
+        density: None,
+        cycles: None,
+        components: None,
+        seed: None,
+    };
+    let mut positional = Vec::new();
+
+    // Парсер флагов
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match arg {
+            "--d2" => cli.outputs.d2_path = Some(take_value(args, &mut i, "a file path")?),
+            "--render" => {
+                cli.outputs.render_path = Some(take_value(args, &mut i, "a file path (e.g. output.svg)")?)
+            }
+            "--open" => cli.outputs.open = true,
+            "--reverse" => cli.outputs.reverse = true,
+            "--max-depth" => cli.max_depth = Some(take_value(args, &mut i, "a number")?),
+            "--max-nodes" => cli.max_nodes = Some(take_value(args, &mut i, "a number")?),
+            "--condense" => cli.transforms.push(Transform::Condense),
+            "--reduce" => cli.transforms.push(Transform::Reduce),
+            "--collapse-chains" => cli.transforms.push(Transform::CollapseChains),
+            "--d2-levels" => cli.outputs.levels = true,
+            "--d2-whole-graph" => cli.outputs.whole_graph = true,
+            "--d2-theme" => cli.d2_theme = Some(take_value(args, &mut i, "light, dark, mono or plain")?),
+            "--json" => cli.json = true,
+            "--bfs" => cli.bfs = true,
+            "--dedupe" => cli.dedupe = Some(take_value(args, &mut i, "marker, reference or off")?),
+            "--no-dedupe" => cli.dedupe = Some("off".to_string()),
+            "--tree-style" => cli.tree_style = Some(take_value(args, &mut i, "indent, unicode or ascii")?),
+            "--depth-prefix" => cli.depth_prefix = true,
+            "--strict" => cli.strict = true,
+            "--color" => cli.color = Some(take_value(args, &mut i, "auto, always or never")?),
+            "--width" => cli.width = Some(take_value(args, &mut i, "a number")?),
+            "--child-order" => {
+                cli.child_order = Some(take_value(args, &mut i, "declaration, alpha, size or depth")?)
+            }
+            "--exit-code" => cli.exit_code = true,
+            "--sort" => cli.sort = Some(take_value(args, &mut i, "a column name")?),
+            "--shortest" => cli.shortest = Some(take_value(args, &mut i, "a number")?),
+            "--save-snapshot" => cli.save_snapshot = Some(take_value(args, &mut i, "a file path")?),
+            "--load-snapshot" => cli.load_snapshot = Some(take_value(args, &mut i, "a file path")?),
+            "--save-test-repo" => cli.save_test_repo = Some(take_value(args, &mut i, "a file path")?),
+            "--anonymize" => cli.anonymize = true,
+            "--nodes" => cli.nodes = Some(take_value(args, &mut i, "a number")?),
+            "--density" => cli.density = Some(take_value(args, &mut i, "a number")?),
+            "--cycles" => cli.cycles = Some(take_value(args, &mut i, "a number")?),
+            "--components" => cli.components = Some(take_value(args, &mut i, "a number")?),
+            "--seed" => cli.seed = Some(take_value(args, &mut i, "a number")?),
+            "--max-cycle-len" => cli.max_cycle_len = Some(take_value(args, &mut i, "a number")?),
+            _ if arg.starts_with("--") => return Err(format!("unknown flag '{}'", arg)),
+            _ => positional.push(arg.to_string()),
+        }
+        i += 1;
+    }
+
+    // generate и bench не читают конфиг, поэтому путь к нему перед ними можно не указывать
+    let mut positional = positional.into_iter().peekable();
+    if let Some(path) = positional.next_if(|p| !matches!(p.as_str(), "generate" | "bench")) {
+        cli.config_path = path;
+    }
+    if let Some(cmd) = positional.next() {
+        cli.command = match cmd.as_str() {
+            "tree" => Command::Tree,
+            "cycles" => Command::Cycles,
+            "order" => Command::Order,
+            "why" => Command::Why(positional.next().ok_or("why requires a package name")?),
+            "stats" => Command::Stats,
+            "diff" => Command::Diff(positional.next().ok_or("diff requires a second config or snapshot path")?),
+            "dominators" => Command::Dominators,
+            "impact" => Command::Impact(positional.next().ok_or("impact requires a package name")?),
+            "query" => Command::Query(positional.next().ok_or("query requires an expression")?),
+            "bench" => Command::Bench(positional.next()),
+            "generate" => Command::Generate(
+                positional.next().ok_or("generate requires a shape (random, chain, diamond, star, self-loop, components)")?,
+                positional.next(),
+            ),
+            other => return Err(format!("unknown command '{}'", other)),
+        };
+    }
+    if let Some(extra) = positional.next() {
+        return Err(format!("unexpected argument '{}'", extra));
+    }
+
+    Ok(cli)
+}
+
+impl CliArgs {
+    /// Переопределение параметров конфигурации флагами командной строки
+    pub fn apply_to(&self, cfg: &mut AppConfig) -> Result<(), ConfigError> {
+        if let Some(v) = &self.max_depth {
+            cfg.max_depth = Some(config::parse_limit("MaxDepth", v, 0)?);
+        }
+        if let Some(v) = &self.max_nodes {
+            cfg.max_nodes = Some(config::parse_limit("MaxNodes", v, 1)?);
+        }
+        if let Some(v) = &self.dedupe {
+            cfg.dedupe = v.parse().map_err(|msg| ConfigError::InvalidValue { field: "Dedupe", msg })?;
+        }
+        if let Some(v) = &self.child_order {
+            cfg.child_order = v.parse().map_err(|msg| ConfigError::InvalidValue { field: "ChildOrder", msg })?;
+        }
+        if let Some(v) = &self.tree_style {
+            cfg.tree_style = v.parse().map_err(|msg| ConfigError::InvalidValue { field: "TreeStyle", msg })?;
+        }
+        if self.depth_prefix {
+            cfg.depth_prefix = true;
+        }
+        if self.strict {
+            cfg.strict = true;
+        }
+        if let Some(v) = &self.d2_theme {
+            cfg.d2_theme = v.parse().map_err(|msg| ConfigError::InvalidValue { field: "D2Theme", msg })?;
+        }
+        if let Some(v) = &self.color {
+            cfg.color = v.parse().map_err(|msg| ConfigError::InvalidValue { field: "Color", msg })?;
+        }
+        if let Some(v) = &self.width {
+            cfg.max_width = Some(config::parse_limit("MaxWidth", v, 1)?);
+        }
+        for t in &self.transforms {
+            if !cfg.transforms.contains(t) {
+                cfg.transforms.push(*t);
+            }
+        }
+        Ok(())
+    }
+
+    /// Параметры команды generate из флагов; --max-depth ограничивает длину путей в графе
+    pub fn generate_params(&self, shape: &str) -> Result<generate::Params, ConfigError> {
+        let shape = shape.parse().map_err(|msg| ConfigError::InvalidValue { field: "shape", msg })?;
+        let mut params = generate::Params::new(shape);
+        if let Some(v) = &self.nodes {
+            params.nodes = config::parse_limit("nodes", v, 1)?;
+        }
+        if let Some(v) = &self.density {
+            params.density = v.trim().parse().map_err(|_| ConfigError::InvalidValue {
+                field: "density",
+                msg: format!("expected a number, got '{}'", v.trim()),
+            })?;
+        }
+        if let Some(v) = &self.cycles {
+            params.cycles = config::parse_limit("cycles", v, 0)?;
+        }
+        if let Some(v) = &self.max_depth {
+            params.max_depth = Some(config::parse_limit("max-depth", v, 1)?);
+        }
+        if let Some(v) = &self.components {
+            params.components = config::parse_limit("components", v, 1)?;
+        }
+        if let Some(v) = &self.seed {
+            params.seed = v.trim().parse().map_err(|_| ConfigError::InvalidValue {
+                field: "seed",
+                msg: format!("expected a non-negative integer, got '{}'", v.trim()),
+            })?;
+        }
+        params.validate().map_err(|msg| ConfigError::InvalidValue { field: "generate", msg })?;
+        Ok(params)
+    }
+}
+
+// Значение флага из следующего аргумента
+fn take_value(args: &[String], i: &mut usize, what: &str) -> Result<String, String> {
+    match args.get(*i + 1) {
+        Some(v) => {
+            *i += 1;
+            Ok(v.clone())
+        }
+        None => Err(format!("{} requires {}", args[*i], what)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<CliArgs, String> {
+        parse_args(&line.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn generate_and_bench_do_not_need_a_config() {
+        let cli = parse("generate random gen.txt --nodes 5").unwrap();
+        assert_eq!(cli.command, Command::Generate("random".into(), Some("gen.txt".into())));
+        assert_eq!(cli.nodes.as_deref(), Some("5"));
+        assert_eq!(parse("bench 1000").unwrap().command, Command::Bench(Some("1000".into())));
+        assert_eq!(parse("bench").unwrap().command, Command::Bench(None));
+
+        // Прежняя запись с путём к конфигу тоже разбирается
+        let cli = parse("my.xml generate chain").unwrap();
+        assert_eq!((cli.config_path.as_str(), cli.command), ("my.xml", Command::Generate("chain".into(), None)));
+        assert!(parse("generate").unwrap_err().starts_with("generate requires a shape"));
+    }
+
+    #[test]
+    fn other_commands_take_the_config_first() {
+        let cli = parse("my.xml why D --shortest 2").unwrap();
+        assert_eq!((cli.config_path.as_str(), cli.command), ("my.xml", Command::Why("D".into())));
+        assert_eq!(parse("").unwrap().config_path, "config.example.xml");
+        assert_eq!(parse("my.xml tree extra").unwrap_err(), "unexpected argument 'extra'");
+        assert_eq!(parse("my.xml launch").unwrap_err(), "unknown command 'launch'");
+    }
+}
@@ -0,0 +1,196 @@
+// Модуль с реализацией команд командной строки (кроме вывода дерева)
+
+use crate::config::AppConfig;
+use crate::diff;
+use crate::dominators;
+use crate::graph::{DependencyGraph, Direction, Limits};
+use crate::json::JsonValue;
+use crate::order;
+use crate::query;
+use crate::render::TextStyle;
+use crate::scc;
+use crate::stats::{self, SortKey};
+use crate::transform;
+use crate::why;
+
+/// Максимальное число перечисляемых циклов
+const MAX_CYCLES: usize = 10_000;
+
+/// Максимальное число перечисляемых путей
+const MAX_PATHS: usize = 10_000;
+
+/// Команда `cycles`: компоненты сильной связности и элементарные циклы
+pub fn run_cycles(graph: &DependencyGraph, cfg: &AppConfig, max_len: Option<usize>) {
+    let graph = graph.filtered(&cfg.exclude_filter);
+    let report = scc::find_cycles(&graph, max_len, MAX_CYCLES);
+
+    println!("\nStrongly connected components with cycles: {}", report.components.len());
+    for comp in &report.components {
+        println!("  {{{}}}", comp.join(", "));
+    }
+
+    match max_len {
+        Some(m) => println!("\nElementary cycles (length <= {}): {}", m, report.cycles.len()),
+        None => println!("\nElementary cycles: {}", report.cycles.len()),
+    }
+    for cycle in &report.cycles {
+        println!("  {}", cycle.join(" -> "));
+    }
+    if report.truncated {
+        println!("  … (stopped after {} cycles)", MAX_CYCLES);
+    }
+}
+
+/// Команда `order`: топологический порядок сборки и уровни параллельной сборки
+pub fn run_order(graph: &DependencyGraph, cfg: &AppConfig, json: bool) {
+    let graph = reachable(graph, cfg);
+    let build = order::build_order(&graph);
+    if json {
+        println!("{}", build.to_json(&cfg.package_name).to_pretty_string());
+    } else {
+        println!("\n{}", build.to_text());
+    }
+}
+
+/// Команда `why <pkg>`: все простые пути (или k кратчайших) от корня до пакета
+pub fn run_why(graph: &DependencyGraph, cfg: &AppConfig, target: &str, k: Option<usize>, json: bool) {
+    let graph = graph.filtered(&cfg.exclude_filter);
+    if !graph.contains(target) {
+        eprintln!("ERROR: package '{}' is not in the graph (or excluded by the filter)", target);
+        return;
+    }
+
+    let report = why::explain(&graph, &cfg.package_name, target, k, cfg.max_depth, MAX_PATHS);
+    if json {
+        println!("{}", report.to_json(&graph).to_pretty_string());
+    } else {
+        println!("\n{}", report.to_text(&graph));
+    }
+}
+
+/// Команда `stats`: метрики узлов и вес прямых зависимостей
+pub fn run_stats(graph: &DependencyGraph, cfg: &AppConfig, sort: SortKey, json: bool) {
+    let graph = reachable(graph, cfg);
+    let mut stats = stats::compute(&graph, &cfg.package_name);
+    stats.sort_by(sort);
+    if json {
+        println!("{}", stats.to_json(&cfg.package_name).to_pretty_string());
+    } else {
+        println!("\n{}", stats.to_text());
+    }
+}
+
+/// Команда `diff`: различия между графом `old` и графом `new`; true, если они есть
+pub fn run_diff(old: &DependencyGraph, new: &DependencyGraph, new_root: &str, cfg: &AppConfig, json: bool) -> bool {
+    // Обе стороны проходят один фильтр и одни преобразования, как при выводе дерева
+    let (old, _) = transform::apply(&old.filtered(&cfg.exclude_filter), &cfg.package_name, &cfg.transforms);
+    let (new, _) = transform::apply(&new.filtered(&cfg.exclude_filter), new_root, &cfg.transforms);
+    let changes = diff::diff(&old, &new);
+    if json {
+        println!("{}", changes.to_json().to_pretty_string());
+    } else {
+        println!("\n{}", changes.to_text());
+    }
+    !changes.is_empty()
+}
+
+/// Команда `dominators`: дерево доминаторов от корня
+pub fn run_dominators(graph: &DependencyGraph, cfg: &AppConfig) {
+    let graph = reachable(graph, cfg);
+    let tree = dominators::dominator_tree(&graph, &cfg.package_name);
+    println!("\nDominator tree of '{}':\n{}", cfg.package_name, tree.to_text(&cfg.package_name));
+}
+
+/// Команда `impact <pkg>`: пакеты, достижимые только через `package`, и граф без него.
+/// Возвращает граф "что если", чтобы по нему можно было сформировать выходные файлы.
+pub fn run_impact(
+    graph: &DependencyGraph,
+    cfg: &AppConfig,
+    package: &str,
+    json: bool,
+) -> Option<DependencyGraph> {
+    let graph = reachable(graph, cfg);
+    if !graph.contains(package) {
+        eprintln!("ERROR: package '{}' is not reachable from '{}'", package, cfg.package_name);
+        return None;
+    }
+    if package == cfg.package_name {
+        eprintln!("ERROR: cannot remove the root package '{}'", package);
+        return None;
+    }
+
+    let tree = dominators::dominator_tree(&graph, &cfg.package_name);
+    let dropped = tree.dominated_by(package);
+    let (what_if, _) = graph
+        .subgraph(|n| n != package)
+        .restrict(&cfg.package_name, "", Direction::Forward, Limits::default());
+
+    if json {
+        let value = JsonValue::object(vec![
+            ("root", cfg.package_name.as_str().into()),
+            ("package", package.into()),
+            ("dropped", JsonValue::strings(&dropped)),
+            ("nodes_before", graph.len().into()),
+            ("nodes_after", what_if.len().into()),
+        ]);
+        println!("{}", value.to_pretty_string());
+    } else {
+        println!(
+            "\nRemoving '{}' drops {} package(s) reachable only through it:\n{}",
+            package,
+            dropped.len(),
+            tree.to_text(package)
+        );
+        println!(
+            "What-if graph without '{}' ({} -> {} node(s)):",
+            package,
+            graph.len(),
+            what_if.len()
+        );
+        what_if.print_tree(&cfg.package_name, &cfg.exclude_filter, cfg.limits(), TextStyle::terminal(cfg));
+    }
+    Some(what_if)
+}
+
+/// Команда `query <expr>`: множество пакетов, найденное запросом.
+/// Возвращает подграф на этих пакетах, чтобы по нему можно было сформировать выходные файлы.
+pub fn run_query(graph: &DependencyGraph, cfg: &AppConfig, expr: &str, json: bool) -> Option<DependencyGraph> {
+    let graph = graph.filtered(&cfg.exclude_filter);
+    let result = match query::run(&graph, expr) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("QUERY ERROR: {}", e);
+            return None;
+        }
+    };
+    let subgraph = graph.subgraph(|n| result.contains(n));
+
+    if json {
+        let edges = subgraph
+            .node_names()
+            .into_iter()
+            .flat_map(|n| subgraph.dependencies(n).map(move |d| (n, d)))
+            .map(|(a, b)| JsonValue::object(vec![("from", a.into()), ("to", b.into())]))
+            .collect();
+        let value = JsonValue::object(vec![
+            ("query", expr.into()),
+            ("nodes", JsonValue::strings(&result)),
+            ("edges", JsonValue::Array(edges)),
+        ]);
+        println!("{}", value.to_pretty_string());
+    } else {
+        println!("\n{} package(s) match '{}':", result.len(), expr);
+        for name in &result {
+            println!("{}", name);
+        }
+    }
+    Some(subgraph)
+}
+
+// Подграф, достижимый из корня, без пакетов, отброшенных фильтром
+fn reachable(graph: &DependencyGraph, cfg: &AppConfig) -> DependencyGraph {
+    let (graph, _) = graph
+        .filtered(&cfg.exclude_filter)
+        .restrict(&cfg.package_name, "", Direction::Forward, Limits::default());
+    graph
+}
@@ -1,19 +1,50 @@
 // Модуль для чтения и парсинга конфигурационного файла (config.xml)
 
 use quick_xml::escape::unescape;
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
 use std::fs;
 use thiserror::Error;
 
+use crate::child_order::ChildOrder;
+use crate::graph::{Dedupe, Limits};
+use crate::output::OutputSpec;
+use crate::d2::D2Theme;
+use crate::render::{ColorMode, TreeStyle};
+use crate::source::Mode;
+use crate::transform::{self, Transform};
+
 /// Структура с параметрами из config.xml
 #[derive(Debug)]
 pub struct AppConfig {
     pub package_name: String,
     pub repo_source: String,
-    pub mode: String,
+    /// Источник зависимостей из реестра в source.rs
+    pub mode: Mode,
     pub ascii_tree: bool,
     pub exclude_filter: String,
+    pub max_depth: Option<usize>,
+    pub max_nodes: Option<usize>,
+    /// Выходные файлы из секции <Output>
+    pub outputs: Vec<OutputSpec>,
+    /// Преобразования графа перед выводом
+    pub transforms: Vec<Transform>,
+    /// Вывод повторно встреченных узлов дерева
+    pub dedupe: Dedupe,
+    /// Порядок потомков в деревьях и экспорте
+    pub child_order: ChildOrder,
+    /// Вид текстового дерева
+    pub tree_style: TreeStyle,
+    /// Номер глубины в начале строк дерева
+    pub depth_prefix: bool,
+    /// Раскраска текстового дерева
+    pub color: ColorMode,
+    /// Ширина, до которой обрезаются строки дерева
+    pub max_width: Option<usize>,
+    /// Строгая проверка тестового репозитория
+    pub strict: bool,
+    /// Тема оформления D2
+    pub d2_theme: D2Theme,
 }
 
 /// Перечисление возможных ошибок при работе с config.xml
@@ -41,10 +72,15 @@ impl AppConfig {
 
         parse_xml(&xml)
     }
+
+    /// Ограничения обхода из конфигурации
+    pub fn limits(&self) -> Limits {
+        Limits { max_depth: self.max_depth, max_nodes: self.max_nodes, dedupe: self.dedupe }
+    }
 }
 
 /// Парсинг содержимого XML-файла и заполнение структуры AppConfig
-fn parse_xml(xml: &str) -> Result<AppConfig, ConfigError> {
+pub fn parse_xml(xml: &str) -> Result<AppConfig, ConfigError> {
     // Инициализация XML-парсера
     let mut reader = Reader::from_str(xml);
     reader.config_mut().trim_text(true);
@@ -57,13 +93,32 @@ fn parse_xml(xml: &str) -> Result<AppConfig, ConfigError> {
     let mut mode: Option<String> = None;
     let mut ascii_tree: Option<String> = None;
     let mut exclude_filter: Option<String> = None;
+    let mut max_depth: Option<String> = None;
+    let mut max_nodes: Option<String> = None;
+    let mut outputs: Vec<OutputSpec> = Vec::new();
+    let mut transforms: Option<String> = None;
+    let mut dedupe: Option<String> = None;
+    let mut child_order: Option<String> = None;
+    let mut tree_style: Option<String> = None;
+    let mut depth_prefix: Option<String> = None;
+    let mut color: Option<String> = None;
+    let mut max_width: Option<String> = None;
+    let mut strict: Option<String> = None;
+    let mut d2_theme: Option<String> = None;
     let mut current_tag: Option<String> = None;
 
     // Чтение и обработка XML-потока
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(e)) => {
-                current_tag = Some(String::from_utf8_lossy(e.name().as_ref()).to_string());
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                if name == "Target" {
+                    outputs.push(parse_target(&e)?);
+                }
+                current_tag = Some(name);
+            }
+            Ok(Event::Empty(e)) if e.name().as_ref() == b"Target" => {
+                outputs.push(parse_target(&e)?);
             }
             Ok(Event::Text(e)) => {
                 if let Some(tag) = &current_tag {
@@ -79,6 +134,17 @@ fn parse_xml(xml: &str) -> Result<AppConfig, ConfigError> {
                         "Mode" => mode = Some(value),
                         "AsciiTree" => ascii_tree = Some(value),
                         "ExcludeFilter" => exclude_filter = Some(value),
+                        "MaxDepth" => max_depth = Some(value),
+                        "MaxNodes" => max_nodes = Some(value),
+                        "Transforms" => transforms = Some(value),
+                        "Dedupe" => dedupe = Some(value),
+                        "ChildOrder" => child_order = Some(value),
+                        "TreeStyle" => tree_style = Some(value),
+                        "DepthPrefix" => depth_prefix = Some(value),
+                        "Color" => color = Some(value),
+                        "MaxWidth" => max_width = Some(value),
+                        "Strict" => strict = Some(value),
+                        "D2Theme" => d2_theme = Some(value),
                         _ => {}
                     }
                 }
@@ -104,35 +170,114 @@ fn parse_xml(xml: &str) -> Result<AppConfig, ConfigError> {
 
     // Проверка корректности поля Mode
     let mode = mode.ok_or(ConfigError::MissingField("Mode"))?;
-    let mode_trim = mode.trim();
-    if mode_trim != "real" && mode_trim != "test" {
-        return Err(ConfigError::InvalidValue {
-            field: "Mode",
-            msg: "expected 'real' or 'test'".into(),
-        });
-    }
+    let mode: Mode = mode
+        .parse()
+        .map_err(|msg| ConfigError::InvalidValue { field: "Mode", msg })?;
 
     // Преобразование строки AsciiTree в булево значение
     let ascii_tree_raw = ascii_tree.ok_or(ConfigError::MissingField("AsciiTree"))?;
-    let ascii_tree_bool = match ascii_tree_raw.trim() {
-        "true" | "True" | "TRUE" => true,
-        "false" | "False" | "FALSE" => false,
-        other => {
-            return Err(ConfigError::InvalidValue {
-                field: "AsciiTree",
-                msg: format!("expected true/false, got '{other}'"),
-            })
-        }
-    };
+    let ascii_tree_bool = parse_bool("AsciiTree", &ascii_tree_raw)?;
 
     let exclude_filter = exclude_filter.unwrap_or_default();
 
+    // Необязательные ограничения обхода
+    let max_depth = max_depth
+        .map(|v| parse_limit("MaxDepth", &v, 0))
+        .transpose()?;
+    let max_nodes = max_nodes
+        .map(|v| parse_limit("MaxNodes", &v, 1))
+        .transpose()?;
+
+    let transforms = transforms
+        .map(|v| transform::parse_list(&v))
+        .transpose()
+        .map_err(|msg| ConfigError::InvalidValue { field: "Transforms", msg })?
+        .unwrap_or_default();
+
+    let dedupe = dedupe
+        .map(|v| v.parse())
+        .transpose()
+        .map_err(|msg| ConfigError::InvalidValue { field: "Dedupe", msg })?
+        .unwrap_or_default();
+
+    let child_order = child_order
+        .map(|v| v.parse())
+        .transpose()
+        .map_err(|msg| ConfigError::InvalidValue { field: "ChildOrder", msg })?
+        .unwrap_or_default();
+
+    // Оформление текстового дерева
+    let tree_style = tree_style
+        .map(|v| v.parse())
+        .transpose()
+        .map_err(|msg| ConfigError::InvalidValue { field: "TreeStyle", msg })?
+        .unwrap_or_default();
+    let depth_prefix = depth_prefix.map(|v| parse_bool("DepthPrefix", &v)).transpose()?.unwrap_or(false);
+    let color = color
+        .map(|v| v.parse())
+        .transpose()
+        .map_err(|msg| ConfigError::InvalidValue { field: "Color", msg })?
+        .unwrap_or_default();
+    let max_width = max_width.map(|v| parse_limit("MaxWidth", &v, 1)).transpose()?;
+    let strict = strict.map(|v| parse_bool("Strict", &v)).transpose()?.unwrap_or(false);
+    let d2_theme = d2_theme
+        .map(|v| v.parse())
+        .transpose()
+        .map_err(|msg| ConfigError::InvalidValue { field: "D2Theme", msg })?
+        .unwrap_or_default();
+
     // Возврат итоговой структуры с загруженными параметрами
     Ok(AppConfig {
         package_name,
         repo_source,
-        mode: mode_trim.to_string(),
+        mode,
         ascii_tree: ascii_tree_bool,
         exclude_filter,
+        max_depth,
+        max_nodes,
+        outputs,
+        transforms,
+        dedupe,
+        child_order,
+        tree_style,
+        depth_prefix,
+        color,
+        max_width,
+        strict,
+        d2_theme,
     })
 }
+
+/// Разбор элемента <Target .../> секции <Output>
+fn parse_target(e: &BytesStart) -> Result<OutputSpec, ConfigError> {
+    let mut attrs = Vec::new();
+    for attr in e.attributes() {
+        let attr = attr.map_err(|err| ConfigError::XmlError(err.to_string()))?;
+        let value = attr
+            .unescape_value()
+            .map_err(|err| ConfigError::XmlError(err.to_string()))?
+            .into_owned();
+        attrs.push((String::from_utf8_lossy(attr.key.as_ref()).to_string(), value));
+    }
+    OutputSpec::from_attributes(&attrs).map_err(|msg| ConfigError::InvalidValue { field: "Output", msg })
+}
+
+/// Разбор булева значения поля
+pub fn parse_bool(field: &'static str, raw: &str) -> Result<bool, ConfigError> {
+    match raw.trim() {
+        "true" | "True" | "TRUE" => Ok(true),
+        "false" | "False" | "FALSE" => Ok(false),
+        other => Err(ConfigError::InvalidValue { field, msg: format!("expected true/false, got '{other}'") }),
+    }
+}
+
+/// Разбор целочисленного ограничения с проверкой минимального значения
+pub fn parse_limit(field: &'static str, raw: &str, min: usize) -> Result<usize, ConfigError> {
+    match raw.trim().parse::<usize>() {
+        Ok(v) if v >= min => Ok(v),
+        _ => Err(ConfigError::InvalidValue {
+            field,
+            msg: format!("expected an integer >= {min}, got '{}'", raw.trim()),
+        }),
+    }
+}
@@ -1,40 +1,299 @@
 // Модуль для формирования D2-представления графа зависимостей
 
-use crate::graph::DependencyGraph;
-use std::collections::HashSet;
-
-pub fn to_d2(graph: &DependencyGraph, reverse: bool) -> String {
-    // Сбор множества уникальных рёбер вида "A -> B"
-    let mut edges = HashSet::<(String, String)>::new();
-
-    for (name, node) in &graph.nodes {
-        for dep in &node.dependencies {
-            if reverse {
-                edges.insert((dep.clone(), name.clone()));
-            } else {
-                edges.insert((name.clone(), dep.clone()));
+use crate::graph::{DependencyGraph, Direction, EdgeKind, Limits};
+use crate::scc;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Тема оформления D2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum D2Theme {
+    /// Светлая тема: корень, оттенки глубины, цветные рёбра по типу
+    #[default]
+    Light,
+    /// Тёмная тема с теми же классами
+    Dark,
+    /// Оттенки серого для печати: типы рёбер различаются только штрихом
+    Mono,
+    /// Без оформления: только узлы и рёбра
+    Plain,
+}
+
+impl FromStr for D2Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "light" => Ok(D2Theme::Light),
+            "dark" => Ok(D2Theme::Dark),
+            "mono" => Ok(D2Theme::Mono),
+            "plain" => Ok(D2Theme::Plain),
+            other => Err(format!("unknown D2 theme '{other}', expected 'light', 'dark', 'mono' or 'plain'")),
+        }
+    }
+}
+
+// Число оттенков глубины; более глубокие узлы получают последний
+const SHADES: usize = 5;
+
+// Цвета темы
+struct Palette {
+    /// Встроенная тема d2 (vars.d2-config.theme-id)
+    theme_id: u32,
+    font: Option<&'static str>,
+    root_fill: &'static str,
+    root_stroke: &'static str,
+    shades: [&'static str; SHADES],
+    cycle: &'static str,
+    dev: &'static str,
+    build: &'static str,
+}
+
+impl D2Theme {
+    fn palette(&self) -> Option<Palette> {
+        match self {
+            D2Theme::Light => Some(Palette {
+                theme_id: 0,
+                font: None,
+                root_fill: "#ffd166",
+                root_stroke: "#e09f3e",
+                shades: ["#e8f1fb", "#cfe2f7", "#b3d1f2", "#95bfec", "#78ade6"],
+                cycle: "#d62828",
+                dev: "#3a86ff",
+                build: "#8338ec",
+            }),
+            D2Theme::Dark => Some(Palette {
+                theme_id: 200,
+                font: Some("#edf2f4"),
+                root_fill: "#e76f51",
+                root_stroke: "#f4a261",
+                shades: ["#2b2d42", "#343752", "#3d4163", "#464b74", "#505685"],
+                cycle: "#ff6b6b",
+                dev: "#4cc9f0",
+                build: "#c77dff",
+            }),
+            D2Theme::Mono => Some(Palette {
+                theme_id: 1,
+                font: None,
+                root_fill: "#ffffff",
+                root_stroke: "#000000",
+                shades: ["#f5f5f5", "#e0e0e0", "#cccccc", "#b8b8b8", "#a3a3a3"],
+                cycle: "#000000",
+                dev: "#555555",
+                build: "#555555",
+            }),
+            D2Theme::Plain => None,
+        }
+    }
+}
+
+/// Формирование D2. При заданных уровнях (подсказка раскладки) узлы одного уровня сборки
+/// группируются в контейнер `level_N`. Тема, кроме `plain`, добавляет заголовок, классы
+/// (корень, глубина от корня, рёбра циклов, dev, build и необязательные рёбра) и легенду
+pub fn to_d2(
+    graph: &DependencyGraph,
+    root: &str,
+    reverse: bool,
+    levels: Option<&HashMap<String, usize>>,
+    theme: D2Theme,
+) -> String {
+    let palette = theme.palette();
+    let styled = palette.is_some();
+
+    // Полный D2-идентификатор узла (с учётом контейнера уровня)
+    let id = |name: &str| match levels.and_then(|l| l.get(name)) {
+        Some(level) => format!("level_{}.{}", level, sanitize(name)),
+        None => sanitize(name),
+    };
+
+    // Глубина узлов от корня в направлении экспорта и компоненты сильной связности для рёбер циклов
+    let direction = if reverse { Direction::Reverse } else { Direction::Forward };
+    let mut depth: HashMap<String, usize> = HashMap::new();
+    let mut component: HashMap<String, usize> = HashMap::new();
+    if styled {
+        let (by_depth, _) = graph.bfs_levels(root, "", Limits::default(), direction);
+        for (d, names) in by_depth.into_iter().enumerate() {
+            depth.extend(names.into_iter().map(|n| (n, d)));
+        }
+        for (c, names) in scc::strongly_connected_components(graph).into_iter().enumerate() {
+            component.extend(names.into_iter().map(|n| (n, c)));
+        }
+    }
+
+    // Уникальные рёбра вида "A -> B" с подписью свёрнутой цепочки и классами: узлы по имени,
+    // потомки в порядке графа, поэтому вывод одинаков от запуска к запуску
+    let mut seen = HashSet::new();
+    let mut edges: Vec<(&str, &str, Option<String>, Vec<&str>)> = Vec::new();
+    let mut used: HashSet<&str> = HashSet::new();
+
+    for name in graph.node_names() {
+        for dep in graph.dependencies(name) {
+            let (a, b) = if reverse { (dep, name) } else { (name, dep) };
+            if !seen.insert((a, b)) {
+                continue;
             }
+            let info = graph.edge_info(name, dep);
+            let label = info.filter(|info| !info.via.is_empty()).map(|info| info.via_label());
+            let mut classes = Vec::new();
+            if styled {
+                match info.map(|i| i.kind) {
+                    Some(EdgeKind::Dev) => classes.push("dev"),
+                    Some(EdgeKind::Build) => classes.push("build"),
+                    _ => {}
+                }
+                if info.is_some_and(|i| i.optional) {
+                    classes.push("optional");
+                }
+                if component.get(name).is_some_and(|c| component.get(dep) == Some(c)) {
+                    classes.push("cycle");
+                }
+                used.extend(classes.iter().copied());
+            }
+            edges.push((a, b, label, classes));
         }
     }
 
+    // Классы узла: оттенок глубины и корень
+    let node_classes = |name: &str| {
+        let mut classes = Vec::new();
+        if let Some(&d) = depth.get(name) {
+            classes.push(format!("depth_{}", d.min(SHADES - 1)));
+        }
+        if styled && name == root {
+            classes.push("root".to_string());
+        }
+        classes
+    };
+
     let mut out = String::new();
-    out.push_str("direction: right\n\n");
+    out.push_str("direction: right\n");
+    if let Some(p) = &palette {
+        out.push_str(&header(p, root, reverse));
+    }
+    out.push('\n');
 
     // Объявление узлов (если будут висячие вершины без рёбер)
-    for name in graph.nodes.keys() {
-        out.push_str(&format!("{}: {}\n", sanitize(name), name));
+    match levels {
+        Some(levels) => {
+            let mut grouped: Vec<Vec<&str>> = Vec::new();
+            for (name, &level) in levels {
+                if grouped.len() <= level {
+                    grouped.resize(level + 1, Vec::new());
+                }
+                grouped[level].push(name);
+            }
+            for names in &mut grouped {
+                names.sort();
+            }
+            for (level, names) in grouped.iter().enumerate() {
+                out.push_str(&format!("level_{}: \"level {}\" {{\n", level, level));
+                for name in names {
+                    out.push_str(&format!("  {}: {}{}\n", sanitize(name), quote(name), classes(&node_classes(name))));
+                }
+                out.push_str("}\n");
+            }
+        }
+        None => {
+            for name in graph.node_names() {
+                out.push_str(&format!("{}: {}{}\n", sanitize(name), quote(name), classes(&node_classes(name))));
+            }
+        }
     }
     out.push('\n');
 
     // Рёбра
-    for (a, b) in edges {
-        out.push_str(&format!("{} -> {}\n", sanitize(&a), sanitize(&b)));
+    for (a, b, label, edge_classes) in &edges {
+        let label = label.as_deref().map(|l| format!(" {}", quote(l))).unwrap_or_default();
+        let sep = if label.is_empty() && edge_classes.is_empty() { "" } else { ":" };
+        out.push_str(&format!("{} -> {}{}{}{}\n", id(a), id(b), sep, label, classes(edge_classes)));
+    }
+
+    if styled {
+        let max_depth = depth.values().copied().max();
+        out.push_str(&legend(graph.contains(root), max_depth, &used));
     }
 
     out
 }
 
-// D2-идентификатор (Оставляет только [A-Za-z0-9_], остальное заменяет на '_')
+// Настройки темы, заголовок и классы
+fn header(p: &Palette, root: &str, reverse: bool) -> String {
+    let title = match reverse {
+        false => format!("Dependencies for '{}'", root),
+        true => format!("Reverse dependencies for '{}'", root),
+    };
+    let font = p.font.map(|f| format!("; font-color: \"{}\"", f)).unwrap_or_default();
+
+    let mut out = format!("vars: {{d2-config: {{theme-id: {}}}}}\n\n", p.theme_id);
+    out.push_str(&format!(
+        "title: {} {{shape: text; near: top-center; style: {{font-size: 24; bold: true{}}}}}\n\n",
+        quote(&title),
+        font
+    ));
+    out.push_str("classes: {\n");
+    out.push_str(&format!(
+        "  root: {{style: {{fill: \"{}\"; stroke: \"{}\"; stroke-width: 3; bold: true}}}}\n",
+        p.root_fill, p.root_stroke
+    ));
+    for (d, shade) in p.shades.iter().enumerate() {
+        out.push_str(&format!("  depth_{}: {{style: {{fill: \"{}\"{}}}}}\n", d, shade, font));
+    }
+    out.push_str(&format!("  cycle: {{style: {{stroke: \"{}\"; stroke-width: 3}}}}\n", p.cycle));
+    out.push_str(&format!("  dev: {{style: {{stroke: \"{}\"; stroke-dash: 5}}}}\n", p.dev));
+    out.push_str(&format!("  build: {{style: {{stroke: \"{}\"; stroke-dash: 2}}}}\n", p.build));
+    out.push_str("  optional: {style: {stroke-dash: 3; opacity: 0.6}}\n");
+    out.push_str("}\n");
+    out
+}
+
+// Легенда: только классы, которые встречаются в диаграмме
+fn legend(has_root: bool, max_depth: Option<usize>, used: &HashSet<&str>) -> String {
+    let mut out = String::from("\nlegend: \"Legend\" {\n  near: bottom-right\n");
+    if has_root {
+        out.push_str("  root: \"root\" {class: root}\n");
+    }
+    if let Some(max) = max_depth {
+        for d in 1..=max.min(SHADES - 1) {
+            let label = if d == SHADES - 1 && max > d { format!("depth {}+", d) } else { format!("depth {}", d) };
+            out.push_str(&format!("  depth_{}: {} {{class: depth_{}}}\n", d, quote(&label), d));
+        }
+    }
+    for (class, label) in [
+        ("cycle", "cycle"),
+        ("dev", "dev-dependency"),
+        ("build", "build-dependency"),
+        ("optional", "optional"),
+    ] {
+        if used.contains(class) {
+            out.push_str(&format!("  {}_from: {} {{shape: text}}\n", class, quote(label)));
+            out.push_str(&format!("  {}_to: \" \" {{shape: text}}\n", class));
+            out.push_str(&format!("  {}_from -> {}_to: {{class: {}}}\n", class, class, class));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+// Ссылка на классы узла или ребра: ` {class: a}` или ` {class: [a; b]}`
+fn classes<S: AsRef<str>>(classes: &[S]) -> String {
+    match classes {
+        [] => String::new(),
+        [one] => format!(" {{class: {}}}", one.as_ref()),
+        many => {
+            let list: Vec<&str> = many.iter().map(AsRef::as_ref).collect();
+            format!(" {{class: [{}]}}", list.join("; "))
+        }
+    }
+}
+
+// Ключевые слова D2 и ключи верхнего уровня оформления, которые не могут быть идентификаторами узлов
+const RESERVED: [&str; 16] = [
+    "direction", "vars", "classes", "class", "title", "legend", "label", "shape", "style", "near", "icon",
+    "tooltip", "link", "width", "height", "layers",
+];
+
+// D2-идентификатор (Оставляет только [A-Za-z0-9_], остальное заменяет на '_';
+// к ключевым словам D2 добавляется '_')
 fn sanitize(s: &str) -> String {
     let mut id = String::with_capacity(s.len());
     for ch in s.chars() {
@@ -44,5 +303,13 @@ fn sanitize(s: &str) -> String {
             id.push('_');
         }
     }
+    if RESERVED.contains(&id.as_str()) {
+        id.push('_');
+    }
     if id.is_empty() { "_".to_string() } else { id }
 }
+
+// Подпись узла в кавычках (имена вида "{A, B}" иначе читаются как блок D2)
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
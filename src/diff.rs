@@ -0,0 +1,198 @@
+// Модуль для сравнения двух графов зависимостей
+
+use std::collections::HashSet;
+
+use crate::graph::DependencyGraph;
+use crate::json::JsonValue;
+
+/// Изменение версии пакета
+#[derive(Debug)]
+pub struct VersionChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Различия между двумя графами
+#[derive(Debug, Default)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub added_edges: Vec<(String, String)>,
+    pub removed_edges: Vec<(String, String)>,
+    pub version_changes: Vec<VersionChange>,
+}
+
+/// Сравнение графов `old` и `new`
+pub fn diff(old: &DependencyGraph, new: &DependencyGraph) -> GraphDiff {
+    let mut d = GraphDiff::default();
+
+    for name in new.node_names() {
+        if !old.contains(name) {
+            d.added_nodes.push(name.to_string());
+            continue;
+        }
+        let (old_version, new_version) = (old.version(name), new.version(name));
+        if old_version != new_version {
+            d.version_changes.push(VersionChange {
+                name: name.to_string(),
+                old: old_version.map(str::to_string),
+                new: new_version.map(str::to_string),
+            });
+        }
+    }
+    for name in old.node_names() {
+        if !new.contains(name) {
+            d.removed_nodes.push(name.to_string());
+        }
+    }
+
+    let old_edges = edges(old);
+    let new_edges = edges(new);
+    d.added_edges = sorted(new_edges.difference(&old_edges));
+    d.removed_edges = sorted(old_edges.difference(&new_edges));
+    d
+}
+
+// Множество рёбер графа
+fn edges(graph: &DependencyGraph) -> HashSet<(String, String)> {
+    graph
+        .node_names()
+        .into_iter()
+        .flat_map(|n| graph.dependencies(n).map(move |d| (n.to_string(), d.to_string())))
+        .collect()
+}
+
+fn sorted<'a>(items: impl Iterator<Item = &'a (String, String)>) -> Vec<(String, String)> {
+    let mut v: Vec<(String, String)> = items.cloned().collect();
+    v.sort();
+    v
+}
+
+impl GraphDiff {
+    /// Отсутствие различий
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.version_changes.is_empty()
+    }
+
+    /// Текстовое представление в стиле unified diff
+    pub fn to_text(&self) -> String {
+        if self.is_empty() {
+            return "No changes\n".to_string();
+        }
+
+        let mut out = String::new();
+        for n in &self.added_nodes {
+            out.push_str(&format!("+ {}\n", n));
+        }
+        for n in &self.removed_nodes {
+            out.push_str(&format!("- {}\n", n));
+        }
+        for (a, b) in &self.added_edges {
+            out.push_str(&format!("+ {} -> {}\n", a, b));
+        }
+        for (a, b) in &self.removed_edges {
+            out.push_str(&format!("- {} -> {}\n", a, b));
+        }
+        for c in &self.version_changes {
+            out.push_str(&format!(
+                "~ {} {} -> {}\n",
+                c.name,
+                c.old.as_deref().unwrap_or("?"),
+                c.new.as_deref().unwrap_or("?")
+            ));
+        }
+        out.push_str(&format!(
+            "\n{} node(s) added, {} removed; {} edge(s) added, {} removed; {} version change(s)\n",
+            self.added_nodes.len(),
+            self.removed_nodes.len(),
+            self.added_edges.len(),
+            self.removed_edges.len(),
+            self.version_changes.len()
+        ));
+        out
+    }
+
+    /// Представление в JSON
+    pub fn to_json(&self) -> JsonValue {
+        let edges = |list: &[(String, String)]| {
+            JsonValue::Array(
+                list.iter()
+                    .map(|(a, b)| JsonValue::object(vec![("from", a.as_str().into()), ("to", b.as_str().into())]))
+                    .collect(),
+            )
+        };
+        let versions = self
+            .version_changes
+            .iter()
+            .map(|c| {
+                JsonValue::object(vec![
+                    ("name", c.name.as_str().into()),
+                    ("old", c.old.clone().into()),
+                    ("new", c.new.clone().into()),
+                ])
+            })
+            .collect();
+
+        JsonValue::object(vec![
+            ("changed", (!self.is_empty()).into()),
+            ("added_nodes", JsonValue::strings(&self.added_nodes)),
+            ("removed_nodes", JsonValue::strings(&self.removed_nodes)),
+            ("added_edges", edges(&self.added_edges)),
+            ("removed_edges", edges(&self.removed_edges)),
+            ("version_changes", JsonValue::Array(versions)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)], versions: &[(&str, &str)]) -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in edges {
+            g.add_edge(from, to);
+        }
+        for (name, version) in versions {
+            g.set_version(name, version);
+        }
+        g
+    }
+
+    fn pairs(edges: &[(String, String)]) -> Vec<String> {
+        edges.iter().map(|(a, b)| format!("{a} -> {b}")).collect()
+    }
+
+    #[test]
+    fn reports_nodes_edges_and_versions() {
+        let old = graph(&[("A", "B"), ("A", "C"), ("C", "D")], &[("A", "1.0"), ("B", "1.0"), ("C", "2.0")]);
+        let new = graph(&[("A", "B"), ("B", "C"), ("A", "E")], &[("A", "1.0"), ("B", "1.1"), ("E", "0.1")]);
+        let d = diff(&old, &new);
+        assert_eq!(d.added_nodes, ["E"]);
+        assert_eq!(d.removed_nodes, ["D"]);
+        assert_eq!(pairs(&d.added_edges), ["A -> E", "B -> C"]);
+        assert_eq!(pairs(&d.removed_edges), ["A -> C", "C -> D"]);
+        let versions: Vec<(&str, Option<&str>, Option<&str>)> =
+            d.version_changes.iter().map(|c| (c.name.as_str(), c.old.as_deref(), c.new.as_deref())).collect();
+        assert_eq!(versions, [("B", Some("1.0"), Some("1.1")), ("C", Some("2.0"), None)]);
+        assert_eq!(
+            d.to_text(),
+            "+ E\n- D\n+ A -> E\n+ B -> C\n- A -> C\n- C -> D\n~ B 1.0 -> 1.1\n~ C 2.0 -> ?\n\n\
+             1 node(s) added, 1 removed; 2 edge(s) added, 2 removed; 2 version change(s)\n"
+        );
+        assert_eq!(d.to_json().get("changed").and_then(JsonValue::as_bool), Some(true));
+    }
+
+    #[test]
+    fn equal_graphs_have_no_changes() {
+        let g = graph(&[("A", "B"), ("B", "A")], &[("A", "1.0")]);
+        let d = diff(&g, &graph(&[("B", "A"), ("A", "B")], &[("A", "1.0")]));
+        assert!(d.is_empty());
+        assert_eq!(d.to_text(), "No changes\n");
+    }
+}
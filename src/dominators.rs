@@ -0,0 +1,180 @@
+// Модуль для построения дерева доминаторов и анализа последствий удаления пакета
+
+use std::collections::HashMap;
+
+use crate::graph::DependencyGraph;
+use crate::scc::IndexedGraph;
+
+/// Дерево доминаторов графа относительно корня
+#[derive(Debug)]
+pub struct DominatorTree {
+    /// Непосредственный доминатор каждого достижимого узла (кроме корня)
+    pub idom: HashMap<String, String>,
+    /// Потомки в дереве доминаторов, по алфавиту
+    pub children: HashMap<String, Vec<String>>,
+}
+
+/// Построение дерева доминаторов (алгоритм Купера - Харви - Кеннеди)
+pub fn dominator_tree(graph: &DependencyGraph, root: &str) -> DominatorTree {
+    let g = IndexedGraph::new(graph);
+    let mut tree = DominatorTree {
+        idom: HashMap::new(),
+        children: HashMap::new(),
+    };
+    let Some(r) = g.names.iter().position(|&n| n == root) else { return tree };
+
+    // Обратный постпорядок достижимых из корня узлов
+    let postorder = postorder(&g.adj, r);
+    let mut po_num = vec![usize::MAX; g.names.len()];
+    for (i, &v) in postorder.iter().enumerate() {
+        po_num[v] = i;
+    }
+    let mut preds: Vec<Vec<usize>> = vec![Vec::new(); g.names.len()];
+    for &v in &postorder {
+        for &w in &g.adj[v] {
+            preds[w].push(v);
+        }
+    }
+
+    const UNDEFINED: usize = usize::MAX;
+    let mut idom = vec![UNDEFINED; g.names.len()];
+    idom[r] = r;
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &v in postorder.iter().rev() {
+            if v == r {
+                continue;
+            }
+            let mut new_idom = UNDEFINED;
+            for &p in &preds[v] {
+                if idom[p] == UNDEFINED {
+                    continue;
+                }
+                new_idom = if new_idom == UNDEFINED { p } else { intersect(&idom, &po_num, p, new_idom) };
+            }
+            if new_idom != UNDEFINED && idom[v] != new_idom {
+                idom[v] = new_idom;
+                changed = true;
+            }
+        }
+    }
+
+    for &v in &postorder {
+        if v != r && idom[v] != UNDEFINED {
+            let (node, dom) = (g.names[v].to_string(), g.names[idom[v]].to_string());
+            tree.children.entry(dom.clone()).or_default().push(node.clone());
+            tree.idom.insert(node, dom);
+        }
+    }
+    for c in tree.children.values_mut() {
+        c.sort();
+    }
+    tree
+}
+
+// Ближайший общий доминатор двух узлов
+fn intersect(idom: &[usize], po_num: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while po_num[a] < po_num[b] {
+            a = idom[a];
+        }
+        while po_num[b] < po_num[a] {
+            b = idom[b];
+        }
+    }
+    a
+}
+
+// Постпорядок обхода в глубину из корня (итеративно)
+fn postorder(adj: &[Vec<usize>], root: usize) -> Vec<usize> {
+    let mut visited = vec![false; adj.len()];
+    let mut order = Vec::new();
+    let mut stack = vec![(root, 0usize)];
+    visited[root] = true;
+    while let Some(frame) = stack.last_mut() {
+        let v = frame.0;
+        if frame.1 < adj[v].len() {
+            let w = adj[v][frame.1];
+            frame.1 += 1;
+            if !visited[w] {
+                visited[w] = true;
+                stack.push((w, 0));
+            }
+        } else {
+            order.push(v);
+            stack.pop();
+        }
+    }
+    order
+}
+
+impl DominatorTree {
+    /// Узлы, доминируемые пакетом (достижимые из корня только через него), без самого пакета
+    pub fn dominated_by(&self, package: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut stack = vec![package.to_string()];
+        while let Some(v) = stack.pop() {
+            for c in self.children.get(&v).into_iter().flatten() {
+                out.push(c.clone());
+                stack.push(c.clone());
+            }
+        }
+        out.sort();
+        out
+    }
+
+    /// Текстовое представление поддерева доминаторов с вершиной `from`
+    pub fn to_text(&self, from: &str) -> String {
+        let mut out = String::new();
+        let mut stack = vec![(from.to_string(), 0usize)];
+        while let Some((v, depth)) = stack.pop() {
+            out.push_str(&format!("{}{}\n", "  ".repeat(depth), v));
+            for c in self.children.get(&v).into_iter().flatten().rev() {
+                stack.push((c.clone(), depth + 1));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in edges {
+            g.add_edge(from, to);
+        }
+        g
+    }
+
+    fn idom(tree: &DominatorTree) -> Vec<(&str, &str)> {
+        let mut pairs: Vec<(&str, &str)> = tree.idom.iter().map(|(n, d)| (n.as_str(), d.as_str())).collect();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn diamond_join_is_dominated_only_by_root() {
+        let g = graph(&[("R", "A"), ("R", "B"), ("A", "J"), ("B", "J"), ("J", "K"), ("X", "A")]);
+        let tree = dominator_tree(&g, "R");
+        assert_eq!(idom(&tree), [("A", "R"), ("B", "R"), ("J", "R"), ("K", "J")]);
+        assert!(tree.dominated_by("A").is_empty());
+        assert_eq!(tree.dominated_by("J"), ["K"]);
+        assert_eq!(tree.dominated_by("R"), ["A", "B", "J", "K"]);
+        assert_eq!(tree.to_text("R"), "R\n  A\n  B\n  J\n    K\n");
+    }
+
+    #[test]
+    fn chain_dominates_everything_below() {
+        let g = graph(&[("R", "A"), ("A", "B"), ("B", "C"), ("C", "A")]);
+        let tree = dominator_tree(&g, "R");
+        assert_eq!(idom(&tree), [("A", "R"), ("B", "A"), ("C", "B")]);
+        assert_eq!(tree.dominated_by("A"), ["B", "C"]);
+        assert_eq!(tree.dominated_by("C"), Vec::<String>::new());
+        assert_eq!(tree.to_text("A"), "A\n  B\n    C\n");
+        assert!(dominator_tree(&g, "missing").idom.is_empty());
+    }
+}
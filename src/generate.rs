@@ -0,0 +1,462 @@
+// Модуль для генерации тестовых репозиториев заданной формы
+//
+// Пакеты нумеруются с нуля и получают имена A, B, ..., Z, AA, AB, ... (допустимые и в строгом
+// режиме), корень - пакет A. Одинаковые параметры и зерно всегда дают один и тот же файл.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use crate::test_repo;
+
+/// Зерно по умолчанию
+pub const DEFAULT_SEED: u64 = 42;
+
+/// Форма генерируемого графа
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// Случайный граф с заданной плотностью, глубиной и числом циклов
+    Random,
+    /// Одна длинная цепочка
+    Chain,
+    /// Ромб: корень зависит от всех промежуточных пакетов, а они - от одного общего
+    Diamond,
+    /// Звезда: корень зависит от всех остальных пакетов
+    Star,
+    /// Цепочка, последний пакет которой зависит от самого себя
+    SelfLoop,
+    /// Несколько несвязанных случайных компонент
+    Components,
+}
+
+impl Shape {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Shape::Random => "random",
+            Shape::Chain => "chain",
+            Shape::Diamond => "diamond",
+            Shape::Star => "star",
+            Shape::SelfLoop => "self-loop",
+            Shape::Components => "components",
+        }
+    }
+
+    // Наименьшее число пакетов, при котором форма имеет смысл
+    fn min_nodes(&self) -> usize {
+        match self {
+            Shape::Diamond => 4,
+            Shape::Star => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "random" => Ok(Shape::Random),
+            "chain" => Ok(Shape::Chain),
+            "diamond" => Ok(Shape::Diamond),
+            "star" => Ok(Shape::Star),
+            "self-loop" => Ok(Shape::SelfLoop),
+            "components" => Ok(Shape::Components),
+            other => Err(format!(
+                "unknown shape '{other}', expected 'random', 'chain', 'diamond', 'star', 'self-loop' or 'components'"
+            )),
+        }
+    }
+}
+
+/// Параметры генерации
+#[derive(Debug, Clone)]
+pub struct Params {
+    pub shape: Shape,
+    /// Число пакетов
+    pub nodes: usize,
+    /// Среднее число зависимостей на пакет (random, components)
+    pub density: f64,
+    /// Число обратных рёбер, каждое из которых замыкает цикл; 0 - ацикличный граф (random, components)
+    pub cycles: usize,
+    /// Наибольшая длина пути без учёта обратных рёбер (random, components)
+    pub max_depth: Option<usize>,
+    /// Число несвязанных компонент (components)
+    pub components: usize,
+    pub seed: u64,
+}
+
+impl Params {
+    /// Параметры по умолчанию для формы
+    pub fn new(shape: Shape) -> Self {
+        Params {
+            shape,
+            nodes: 10,
+            density: 1.5,
+            cycles: 0,
+            max_depth: None,
+            components: if shape == Shape::Components { 3 } else { 1 },
+            seed: DEFAULT_SEED,
+        }
+    }
+
+    /// Проверка совместимости параметров
+    pub fn validate(&self) -> Result<(), String> {
+        if self.nodes < self.shape.min_nodes() {
+            return Err(format!("shape '{}' needs at least {} packages", self.shape.as_str(), self.shape.min_nodes()));
+        }
+        if !self.density.is_finite() || self.density < 0.0 {
+            return Err(format!("density must be a non-negative number, got {}", self.density));
+        }
+        if self.components > self.nodes {
+            return Err(format!("cannot split {} packages into {} components", self.nodes, self.components));
+        }
+        // Каждый пакет, кроме корня компоненты, даёт хотя бы одно обратное ребро (к родителю),
+        // поэтому в компоненте из n пакетов гарантированно замыкается n - 1 цикл
+        if matches!(self.shape, Shape::Random | Shape::Components) {
+            for c in 0..self.components {
+                let size = component_start(self, c + 1) - component_start(self, c);
+                let cycles = component_cycles(self, c);
+                if cycles >= size {
+                    return Err(format!(
+                        "cannot close {} cycle(s) in a component of {} package(s), at most {} per component",
+                        cycles,
+                        size,
+                        size - 1
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Запись параметров для заголовка файла
+    fn describe(&self) -> String {
+        let mut s = format!("{} nodes={}", self.shape.as_str(), self.nodes);
+        if matches!(self.shape, Shape::Random | Shape::Components) {
+            let _ = write!(s, " density={} cycles={}", self.density, self.cycles);
+            if let Some(d) = self.max_depth {
+                let _ = write!(s, " max-depth={}", d);
+            }
+            if self.shape == Shape::Components {
+                let _ = write!(s, " components={}", self.components);
+            }
+            let _ = write!(s, " seed={}", self.seed);
+        }
+        s
+    }
+}
+
+/// Списки зависимостей пакетов (по номерам) для заданных параметров
+pub fn generate(params: &Params) -> Vec<Vec<usize>> {
+    let n = params.nodes;
+    let mut deps = vec![Vec::new(); n];
+    match params.shape {
+        Shape::Chain | Shape::SelfLoop => {
+            for i in 1..n {
+                deps[i - 1].push(i);
+            }
+            if params.shape == Shape::SelfLoop {
+                deps[n - 1].push(n - 1);
+            }
+        }
+        Shape::Diamond => {
+            deps[0] = (1..n - 1).collect();
+            for d in &mut deps[1..n - 1] {
+                d.push(n - 1);
+            }
+        }
+        Shape::Star => deps[0] = (1..n).collect(),
+        Shape::Random | Shape::Components => {
+            let mut rng = params.seed;
+            let k = params.components;
+            for c in 0..k {
+                // Компоненты - последовательные отрезки номеров
+                let range = component_start(params, c)..component_start(params, c + 1);
+                random_component(&mut deps, range, params, component_cycles(params, c), &mut rng);
+            }
+        }
+    }
+    deps
+}
+
+// Случайная компонента на отрезке номеров: случайный остов с корнем в первом пакете
+// (все пакеты достижимы из него), дополнительные рёбра вглубь остова и обратные рёбра
+fn random_component(
+    deps: &mut [Vec<usize>],
+    range: std::ops::Range<usize>,
+    params: &Params,
+    cycles: usize,
+    rng: &mut u64,
+) {
+    let start = range.start;
+    let size = range.len();
+    let max_depth = params.max_depth.unwrap_or(usize::MAX);
+
+    // Родитель - случайный из предыдущих пакетов, под которым ещё можно углубиться
+    let mut level = vec![0; size];
+    let mut parent = vec![0; size];
+    let mut candidates = vec![0];
+    let mut edges = HashSet::new();
+    for i in 1..size {
+        let p = candidates[random_below(rng, candidates.len())];
+        parent[i] = p;
+        level[i] = level[p] + 1;
+        if level[i] < max_depth {
+            candidates.push(i);
+        }
+        edges.insert((p, i));
+    }
+
+    // Дополнительные рёбра ведут только на более глубокий уровень остова, поэтому граф
+    // остаётся ацикличным, а длина любого пути не превышает max_depth
+    let target = ((params.density * size as f64).round() as usize).max(edges.len());
+    let mut attempts = 0;
+    while edges.len() < target && attempts < 20 * target {
+        attempts += 1;
+        let (a, b) = (random_below(rng, size), random_below(rng, size));
+        if level[a] < level[b] {
+            edges.insert((a, b));
+        }
+    }
+    let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+    edges.sort_unstable();
+
+    // Обратное ребро от пакета к одному из его предков в остове замыкает цикл
+    let mut back = HashSet::new();
+    let mut attempts = 0;
+    while back.len() < cycles && attempts < 20 * cycles + 100 {
+        attempts += 1;
+        let from = 1 + random_below(rng, size - 1);
+        let mut to = parent[from];
+        for _ in 0..random_below(rng, level[from]) {
+            to = parent[to];
+        }
+        back.insert((from, to));
+    }
+    // Если случайных попыток не хватило, недостающие циклы замыкаются рёбрами к родителю
+    for (from, &to) in parent.iter().enumerate().skip(1) {
+        if back.len() >= cycles {
+            break;
+        }
+        back.insert((from, to));
+    }
+    let mut back: Vec<(usize, usize)> = back.into_iter().collect();
+    back.sort_unstable();
+
+    for (a, b) in edges.into_iter().chain(back) {
+        deps[start + a].push(start + b);
+    }
+}
+
+/// Текст тестового репозитория: заголовок с параметрами и по строке на пакет
+pub fn to_test_repo(params: &Params, deps: &[Vec<usize>]) -> String {
+    let roots: Vec<String> = (0..params.components).map(|c| test_repo::letter_name(component_start(params, c))).collect();
+    let mut out = format!("# generated: {}\n# root: {}\n", params.describe(), roots.join(" "));
+    for (i, d) in deps.iter().enumerate() {
+        out.push_str(&test_repo::letter_name(i));
+        out.push(':');
+        for &j in d {
+            out.push(' ');
+            out.push_str(&test_repo::letter_name(j));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Число циклов в компоненте: циклы распределяются по компонентам поровну
+fn component_cycles(params: &Params, c: usize) -> usize {
+    params.cycles / params.components + usize::from(c < params.cycles % params.components)
+}
+
+// Номер первого пакета компоненты
+fn component_start(params: &Params, c: usize) -> usize {
+    c * params.nodes / params.components
+}
+
+// Случайное число из [0, bound); при bound = 0 возвращает 0
+fn random_below(rng: &mut u64, bound: usize) -> usize {
+    if bound == 0 { 0 } else { next_random(rng) as usize % bound }
+}
+
+/// Линейный конгруэнтный генератор (параметры из MMIX)
+pub fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    *state >> 33
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::DependencyGraph;
+    use crate::scc;
+
+    // Параметры для проверки свойств: разные размеры, плотности, глубины и зёрна
+    fn cases(shape: Shape, cycles: usize) -> Vec<Params> {
+        let mut cases = Vec::new();
+        for seed in 0..20 {
+            for (nodes, density, max_depth) in [(1, 1.5, None), (7, 0.5, Some(1)), (30, 2.0, Some(3)), (100, 3.0, None)] {
+                let mut params = Params::new(shape);
+                params.nodes = nodes;
+                params.density = density;
+                params.max_depth = max_depth;
+                params.seed = seed;
+                params.cycles = cycles;
+                if shape == Shape::Components {
+                    params.components = 1 + seed as usize % 4;
+                }
+                if params.validate().is_ok() {
+                    cases.push(params);
+                }
+            }
+        }
+        assert!(!cases.is_empty());
+        cases
+    }
+
+    fn to_graph(deps: &[Vec<usize>]) -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        for (i, d) in deps.iter().enumerate() {
+            graph.ensure_node(&test_repo::letter_name(i));
+            for &j in d {
+                graph.add_edge(&test_repo::letter_name(i), &test_repo::letter_name(j));
+            }
+        }
+        graph
+    }
+
+    // Длина самого длинного пути в ацикличном графе (в рёбрах)
+    fn longest_path(deps: &[Vec<usize>]) -> usize {
+        fn from(i: usize, deps: &[Vec<usize>], memo: &mut [Option<usize>]) -> usize {
+            if let Some(len) = memo[i] {
+                return len;
+            }
+            let len = deps[i].iter().map(|&j| 1 + from(j, deps, memo)).max().unwrap_or(0);
+            memo[i] = Some(len);
+            len
+        }
+        let mut memo = vec![None; deps.len()];
+        (0..deps.len()).map(|i| from(i, deps, &mut memo)).max().unwrap_or(0)
+    }
+
+    // Число компонент слабой связности
+    fn weak_components(deps: &[Vec<usize>]) -> usize {
+        let mut parent: Vec<usize> = (0..deps.len()).collect();
+        fn find(parent: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while parent[root] != root {
+                root = parent[root];
+            }
+            parent[i] = root;
+            root
+        }
+        for (i, d) in deps.iter().enumerate() {
+            for &j in d {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+        (0..deps.len()).filter(|&i| find(&mut parent, i) == i).count()
+    }
+
+    // Пакеты, достижимые из `root`
+    fn reachable(deps: &[Vec<usize>], root: usize) -> Vec<bool> {
+        let mut seen = vec![false; deps.len()];
+        let mut stack = vec![root];
+        seen[root] = true;
+        while let Some(i) = stack.pop() {
+            for &j in &deps[i] {
+                if !seen[j] {
+                    seen[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn acyclic_without_cycles() {
+        for shape in [Shape::Random, Shape::Components] {
+            for params in cases(shape, 0) {
+                let deps = generate(&params);
+                let components = scc::strongly_connected_components(&to_graph(&deps));
+                assert!(components.iter().all(|c| c.len() == 1), "{}", params.describe());
+                assert!(deps.iter().enumerate().all(|(i, d)| !d.contains(&i)), "{}", params.describe());
+            }
+        }
+    }
+
+    #[test]
+    fn paths_respect_max_depth() {
+        for shape in [Shape::Random, Shape::Components] {
+            for params in cases(shape, 0).into_iter().filter(|p| p.max_depth.is_some()) {
+                let deps = generate(&params);
+                assert!(longest_path(&deps) <= params.max_depth.unwrap(), "{}", params.describe());
+            }
+        }
+    }
+
+    #[test]
+    fn components_are_separate_and_reachable_from_roots() {
+        for cycles in [0, 3] {
+            for params in cases(Shape::Components, cycles).into_iter().chain(cases(Shape::Random, cycles)) {
+                let deps = generate(&params);
+                assert_eq!(weak_components(&deps), params.components, "{}", params.describe());
+                for c in 0..params.components {
+                    let seen = reachable(&deps, component_start(&params, c));
+                    let range = component_start(&params, c)..component_start(&params, c + 1);
+                    assert!(range.clone().all(|i| seen[i]), "{}", params.describe());
+                    assert!((0..deps.len()).filter(|i| !range.contains(i)).all(|i| !seen[i]), "{}", params.describe());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn closes_requested_cycles() {
+        for params in cases(Shape::Random, 4) {
+            let deps = generate(&params);
+            let mut acyclic = params.clone();
+            acyclic.cycles = 0;
+            let edges = |deps: &[Vec<usize>]| deps.iter().map(Vec::len).sum::<usize>();
+            assert_eq!(edges(&deps), edges(&generate(&acyclic)) + 4, "{}", params.describe());
+            let components = scc::strongly_connected_components(&to_graph(&deps));
+            assert!(components.iter().any(|c| c.len() > 1), "{}", params.describe());
+        }
+
+        // Две вершины допускают только один цикл
+        let mut params = Params::new(Shape::Random);
+        params.nodes = 2;
+        params.cycles = 1;
+        assert_eq!(generate(&params), vec![vec![1], vec![0]]);
+        params.cycles = 5;
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn same_seed_gives_same_graph() {
+        for params in cases(Shape::Components, 2) {
+            assert_eq!(generate(&params), generate(&params));
+        }
+        let mut a = Params::new(Shape::Random);
+        a.nodes = 50;
+        let mut b = a.clone();
+        b.seed += 1;
+        assert_ne!(generate(&a), generate(&b));
+    }
+
+    #[test]
+    fn fixed_shapes() {
+        let shape = |shape, nodes| {
+            let mut params = Params::new(shape);
+            params.nodes = nodes;
+            generate(&params)
+        };
+        assert_eq!(shape(Shape::Chain, 3), vec![vec![1], vec![2], vec![]]);
+        assert_eq!(shape(Shape::SelfLoop, 2), vec![vec![1], vec![1]]);
+        assert_eq!(shape(Shape::Star, 3), vec![vec![1, 2], vec![], vec![]]);
+        assert_eq!(shape(Shape::Diamond, 4), vec![vec![1, 2], vec![3], vec![3], vec![]]);
+    }
+}
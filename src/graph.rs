@@ -1,154 +1,780 @@
 // Модуль для построения и обхода графа зависимостей
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
 
-/// Узел графа, представляющий отдельный пакет и его зависимости
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
-pub struct PackageNode {
-    pub name: String,
-    pub dependencies: Vec<String>,
+use crate::render::{TextRenderer, TextStyle};
+
+/// Тип зависимости (секция Cargo.toml, из которой она получена)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeKind {
+    #[default]
+    Normal,
+    Dev,
+    Build,
+}
+
+impl EdgeKind {
+    /// Краткое имя типа
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::Normal => "normal",
+            EdgeKind::Dev => "dev",
+            EdgeKind::Build => "build",
+        }
+    }
+}
+
+impl std::str::FromStr for EdgeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "normal" => Ok(EdgeKind::Normal),
+            "dev" => Ok(EdgeKind::Dev),
+            "build" => Ok(EdgeKind::Build),
+            other => Err(format!("unknown dependency kind '{other}', expected 'normal', 'dev' or 'build'")),
+        }
+    }
+}
+
+/// Дополнительные сведения о ребре, если источник их предоставляет
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EdgeInfo {
+    pub kind: EdgeKind,
+    pub optional: bool,
+    pub features: Vec<String>,
+    /// Промежуточные узлы свёрнутой цепочки, которую заменяет ребро
+    pub via: Vec<String>,
 }
 
-/// Структура графа зависимостей
-#[derive(Debug)]
+impl EdgeInfo {
+    /// Краткая подпись: тип, необязательность и фичи
+    pub fn label(&self) -> String {
+        let mut parts = vec![self.kind.as_str().to_string()];
+        if self.optional {
+            parts.push("optional".into());
+        }
+        if !self.features.is_empty() {
+            parts.push(format!("features: {}", self.features.join(", ")));
+        }
+        if !self.via.is_empty() {
+            parts.push(self.via_label());
+        }
+        parts.join(", ")
+    }
+
+    /// Подпись свёрнутой цепочки: "via B → C"
+    pub fn via_label(&self) -> String {
+        format!("via {}", self.via.join(" → "))
+    }
+}
+
+/// Идентификатор узла графа (индекс в таблице интернированных имён)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// Индекс узла для таблиц вида Vec<_>
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Идентификатор по индексу из диапазона 0..graph.len()
+    pub fn from_index(index: usize) -> Self {
+        NodeId(index as u32)
+    }
+}
+
+/// Списки смежности узла в обе стороны
+#[derive(Debug, Clone, Default)]
+struct Adjacency {
+    /// Зависимости в порядке добавления
+    dependencies: Vec<NodeId>,
+    /// Пакеты, зависящие от узла, в порядке добавления рёбер
+    dependents: Vec<NodeId>,
+}
+
+// Пустой набор атрибутов для узлов, у которых их нет
+static NO_ATTRIBUTES: BTreeMap<String, String> = BTreeMap::new();
+
+/// Структура графа зависимостей. Имена узлов интернированы: каждое хранится один раз,
+/// списки смежности и рёбра содержат только идентификаторы
+#[derive(Debug, Default)]
 pub struct DependencyGraph {
-    pub nodes: HashMap<String, PackageNode>,
+    /// Имена узлов по идентификаторам
+    names: Vec<Rc<str>>,
+    /// Идентификаторы узлов по именам (ключи разделяют память с `names`)
+    index: HashMap<Rc<str>, NodeId>,
+    adjacency: Vec<Adjacency>,
+    /// Версии пакетов по идентификаторам
+    versions: Vec<Option<Box<str>>>,
+    /// Произвольные атрибуты пакетов (лицензия, источник и т.п.); хранятся только непустые
+    attributes: HashMap<NodeId, BTreeMap<String, String>>,
+    /// Множество рёбер для проверки дубликатов за O(1)
+    edges: HashSet<(NodeId, NodeId)>,
+    /// Сведения о рёбрах (package, depends_on), если они известны
+    edge_info: HashMap<(NodeId, NodeId), EdgeInfo>,
+    /// Узлы, зависимости которых были отсечены при построении графа, и причина отсечения
+    pub truncated: HashMap<String, &'static str>,
 }
 
 /// Блок реализации структуры DependencyGraph
 impl DependencyGraph {
     // Создание пустого графа зависимостей
     pub fn new() -> Self {
-        Self {
-            nodes: HashMap::new(),
+        Self::default()
+    }
+
+    /// Проверка присутствия пакета в графе (пакет добавляется, если его нет)
+    pub fn ensure_node(&mut self, name: &str) -> NodeId {
+        if let Some(&id) = self.index.get(name) {
+            return id;
         }
+        let id = NodeId(self.names.len() as u32);
+        let name: Rc<str> = Rc::from(name);
+        self.names.push(name.clone());
+        self.index.insert(name, id);
+        self.adjacency.push(Adjacency::default());
+        self.versions.push(None);
+        id
+    }
+
+    /// Идентификатор узла по имени
+    pub fn id(&self, name: &str) -> Option<NodeId> {
+        self.index.get(name).copied()
+    }
+
+    /// Имя узла по идентификатору
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.names[id.index()]
     }
 
-    /// Проверка присутствия пакета в графе
-    pub fn ensure_node(&mut self, name: &str) {
-        self.nodes
-            .entry(name.to_string())
-            .or_insert_with(|| PackageNode { name: name.to_string(), dependencies: Vec::new() });
+    /// Есть ли пакет в графе
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    /// Число узлов
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Число рёбер (используется командой bench)
+    #[cfg_attr(not(feature = "bench"), allow(dead_code))]
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
     }
 
     /// Добавление ориентированного ребра package -> depends_on
     pub fn add_edge(&mut self, package: &str, depends_on: &str) {
-        self.ensure_node(package);
-        self.ensure_node(depends_on);
-        if let Some(node) = self.nodes.get_mut(package) {
-            if !node.dependencies.contains(&depends_on.to_string()) {
-                node.dependencies.push(depends_on.to_string());
-            }
+        let from = self.ensure_node(package);
+        let to = self.ensure_node(depends_on);
+        if self.edges.insert((from, to)) {
+            self.adjacency[from.index()].dependencies.push(to);
+            self.adjacency[to.index()].dependents.push(from);
         }
     }
 
-    /// Загрузка полного графа из "карты тестового репозитория"
-    pub fn load_from_map(&mut self, map: &HashMap<String, Vec<String>>) {
-        for (pkg, deps) in map {
-            self.ensure_node(pkg);
-            for d in deps {
-                self.add_edge(pkg, d);
-            }
+    /// Добавление ребра с дополнительными сведениями о нём
+    pub fn add_edge_with(&mut self, package: &str, depends_on: &str, info: EdgeInfo) {
+        self.add_edge(package, depends_on);
+        if let (Some(from), Some(to)) = (self.id(package), self.id(depends_on)) {
+            self.edge_info.insert((from, to), info);
         }
     }
 
-    /// Построение обратного отображения
-    pub fn build_reverse_index(&self) -> HashMap<String, Vec<String>> {
-        let mut rev: HashMap<String, Vec<String>> = HashMap::new();
-        for (pkg, node) in &self.nodes {
-            for dep in &node.dependencies {
-                rev.entry(dep.clone()).or_default().push(pkg.clone());
-            }
+    /// Удаление ребра package -> depends_on вместе со сведениями о нём
+    pub fn remove_edge(&mut self, package: &str, depends_on: &str) {
+        let (Some(from), Some(to)) = (self.id(package), self.id(depends_on)) else { return };
+        if self.edges.remove(&(from, to)) {
+            self.adjacency[from.index()].dependencies.retain(|&d| d != to);
+            self.adjacency[to.index()].dependents.retain(|&d| d != from);
+            self.edge_info.remove(&(from, to));
         }
-        rev
     }
 
-    /// Вывод дерева прямых зависимостей с фильтром по подстроке
-    pub fn print_tree(&self, root: &str, exclude_filter: &str) {
-        let mut visited = HashSet::new();
-        let mut stack: Vec<String> = Vec::new();
-        self.dfs_forward(root, exclude_filter, &mut visited, &mut stack);
+    /// Сведения о ребре package -> depends_on
+    pub fn edge_info(&self, package: &str, depends_on: &str) -> Option<&EdgeInfo> {
+        self.edge_info.get(&(self.id(package)?, self.id(depends_on)?))
     }
 
-    fn dfs_forward(
-        &self,
-        package: &str,
-        exclude_filter: &str,
-        visited: &mut HashSet<String>,
-        stack: &mut Vec<String>,
-    ) {
-        if let Some(f) = exclude_nonempty(exclude_filter) {
-            if package.contains(f) {
-                return;
-            }
+    /// Установка версии пакета
+    pub fn set_version(&mut self, package: &str, version: &str) {
+        let id = self.ensure_node(package);
+        self.versions[id.index()] = Some(version.into());
+    }
+
+    /// Версия пакета, если известна
+    pub fn version(&self, package: &str) -> Option<&str> {
+        self.versions[self.id(package)?.index()].as_deref()
+    }
+
+    /// Установка атрибута пакета
+    pub fn set_attribute(&mut self, package: &str, key: &str, value: &str) {
+        let id = self.ensure_node(package);
+        self.attributes.entry(id).or_default().insert(key.to_string(), value.to_string());
+    }
+
+    /// Атрибуты пакета
+    pub fn attributes(&self, package: &str) -> Option<&BTreeMap<String, String>> {
+        self.id(package).map(|id| self.attributes.get(&id).unwrap_or(&NO_ATTRIBUTES))
+    }
+
+    /// Прямые зависимости пакета
+    pub fn dependencies<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> + 'a {
+        self.id(name)
+            .into_iter()
+            .flat_map(|id| self.successors(id).iter().map(|&d| self.name(d)))
+    }
+
+    /// Пакеты, напрямую зависящие от пакета
+    pub fn dependents<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> + 'a {
+        self.id(name)
+            .into_iter()
+            .flat_map(|id| self.predecessors(id).iter().map(|&d| self.name(d)))
+    }
+
+    /// Зависимости узла по идентификатору
+    pub fn successors(&self, id: NodeId) -> &[NodeId] {
+        &self.adjacency[id.index()].dependencies
+    }
+
+    /// Зависящие от узла пакеты по идентификатору
+    pub fn predecessors(&self, id: NodeId) -> &[NodeId] {
+        &self.adjacency[id.index()].dependents
+    }
+
+    // Соседи узла в заданном направлении обхода
+    fn neighbors(&self, id: NodeId, direction: Direction) -> &[NodeId] {
+        match direction {
+            Direction::Forward => self.successors(id),
+            Direction::Reverse => self.predecessors(id),
         }
+    }
 
-        // Проверка бесконечных циклов
-        if stack.contains(&package.to_string()) {
-            println!("{}{} (cycle)", "  ".repeat(stack.len()), package);
-            return;
+    /// Упорядочивание соседей каждого узла в заданном направлении по ключу (сортировка устойчивая)
+    pub fn sort_children<K: Ord>(&mut self, direction: Direction, mut key: impl FnMut(NodeId) -> K) {
+        for adjacency in &mut self.adjacency {
+            let list = match direction {
+                Direction::Forward => &mut adjacency.dependencies,
+                Direction::Reverse => &mut adjacency.dependents,
+            };
+            list.sort_by_cached_key(|&n| key(n));
         }
-        if visited.contains(package) {
-            println!("{}{} (visited)", "  ".repeat(stack.len()), package);
-            return;
+    }
+
+    /// Имена всех узлов в алфавитном порядке
+    pub fn node_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.names.iter().map(|n| &**n).collect();
+        names.sort();
+        names
+    }
+
+    /// Подграф из узлов, удовлетворяющих условию, и рёбер между ними.
+    /// Порядок узлов и соседей в обоих направлениях сохраняется
+    pub fn subgraph(&self, keep: impl Fn(&str) -> bool) -> DependencyGraph {
+        let mut out = DependencyGraph::new();
+        let mut new_id: Vec<Option<NodeId>> = vec![None; self.len()];
+        for (i, name) in self.names.iter().enumerate() {
+            if !keep(name) {
+                continue;
+            }
+            let id = out.ensure_node(name);
+            new_id[i] = Some(id);
+            out.versions[id.index()] = self.versions[i].clone();
+            if let Some(attributes) = self.attributes.get(&NodeId::from_index(i)) {
+                out.attributes.insert(id, attributes.clone());
+            }
+        }
+        let map = |list: &[NodeId]| -> Vec<NodeId> { list.iter().filter_map(|n| new_id[n.index()]).collect() };
+        for (i, adjacency) in self.adjacency.iter().enumerate() {
+            let Some(id) = new_id[i] else { continue };
+            out.adjacency[id.index()] = Adjacency {
+                dependencies: map(&adjacency.dependencies),
+                dependents: map(&adjacency.dependents),
+            };
+        }
+        for &(from, to) in &self.edges {
+            if let (Some(a), Some(b)) = (new_id[from.index()], new_id[to.index()]) {
+                out.edges.insert((a, b));
+                if let Some(info) = self.edge_info.get(&(from, to)) {
+                    out.edge_info.insert((a, b), info.clone());
+                }
+            }
         }
+        for (name, reason) in &self.truncated {
+            if out.contains(name) {
+                out.truncated.insert(name.clone(), reason);
+            }
+        }
+        out
+    }
 
-        println!("{}{}", "  ".repeat(stack.len()), package);
-        visited.insert(package.to_string());
-        stack.push(package.to_string());
+    /// Граф без пакетов, имя которых содержит подстроку фильтра
+    pub fn filtered(&self, exclude_filter: &str) -> DependencyGraph {
+        match exclude_nonempty(exclude_filter) {
+            Some(f) => self.subgraph(|name| !name.contains(f)),
+            None => self.subgraph(|_| true),
+        }
+    }
 
-        if let Some(node) = self.nodes.get(package) {
-            for dep in &node.dependencies {
-                self.dfs_forward(dep, exclude_filter, visited, stack);
+    /// Ограничение графа узлами, достижимыми из `root` в пределах лимитов.
+    /// Обход в ширину, чтобы при нехватке бюджета узлов сохранялись ближайшие к корню пакеты.
+    /// Пакеты, отброшенные фильтром, остаются листьями (дерево помечает их как отфильтрованные),
+    /// но не раскрываются и не расходуют бюджет узлов
+    pub fn restrict(
+        &self,
+        root: &str,
+        exclude_filter: &str,
+        direction: Direction,
+        limits: Limits,
+    ) -> (DependencyGraph, Truncation) {
+        let filter = exclude_nonempty(exclude_filter);
+        let excluded = |id: NodeId| filter.is_some_and(|f| self.name(id).contains(f));
+        let mut kept = vec![false; self.len()];
+        let mut kept_count = 0;
+        let mut skipped: HashSet<NodeId> = HashSet::new();
+        let mut truncation = Truncation::default();
+        let mut cut: HashMap<String, &'static str> = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        if let Some(r) = self.id(root) {
+            kept[r.index()] = true;
+            kept_count += 1;
+            queue.push_back((r, 0usize));
+        }
+
+        while let Some((id, depth)) = queue.pop_front() {
+            let next = self.neighbors(id, direction);
+            if limits.depth_reached(depth) {
+                if !next.is_empty() {
+                    truncation.depth_cut += 1;
+                    cut.insert(self.name(id).to_string(), "depth limit");
+                }
+                continue;
+            }
+            for &n in next {
+                if kept[n.index()] {
+                    continue;
+                }
+                if excluded(n) {
+                    kept[n.index()] = true;
+                    continue;
+                }
+                if limits.nodes_reached(kept_count) {
+                    skipped.insert(n);
+                    cut.entry(self.name(id).to_string()).or_insert("node limit");
+                    continue;
+                }
+                kept[n.index()] = true;
+                kept_count += 1;
+                queue.push_back((n, depth + 1));
             }
         }
+        truncation.node_cut = skipped.len();
 
-        stack.pop();
+        // Перенос узлов и рёбер между сохранёнными узлами
+        let mut limited = self.subgraph(|name| self.id(name).is_some_and(|id| kept[id.index()]));
+        limited.truncated = cut;
+        (limited, truncation)
+    }
+
+    /// Вывод дерева прямых зависимостей с фильтром по подстроке
+    pub fn print_tree(&self, root: &str, exclude_filter: &str, limits: Limits, style: TextStyle) -> Truncation {
+        let (text, truncation) = self.render_tree(root, exclude_filter, limits, Direction::Forward, style);
+        print!("{}", text);
+        truncation
     }
 
     /// Вывод обратных зависимостей для `target`
-    pub fn print_reverse_tree(&self, target: &str, exclude_filter: &str) {
-        let rev = self.build_reverse_index();
-        let mut visited = HashSet::new();
-        let mut stack = Vec::new();
-        self.dfs_reverse(target, exclude_filter, &rev, &mut visited, &mut stack);
+    pub fn print_reverse_tree(&self, target: &str, exclude_filter: &str, limits: Limits, style: TextStyle) -> Truncation {
+        let (text, truncation) = self.render_tree(target, exclude_filter, limits, Direction::Reverse, style);
+        print!("{}", text);
+        truncation
+    }
+
+    /// Формирование текста дерева зависимостей в заданном направлении
+    pub fn render_tree(
+        &self,
+        root: &str,
+        exclude_filter: &str,
+        limits: Limits,
+        direction: Direction,
+        style: TextStyle,
+    ) -> (String, Truncation) {
+        let mut text = TextRenderer::new(style);
+        let truncation = self.walk(root, exclude_filter, limits, direction, &mut text);
+        (text.finish(), truncation)
     }
 
-    fn dfs_reverse(
+    /// Обход дерева зависимостей в глубину с передачей событий посетителю.
+    /// Обход итеративный, поэтому глубина графа не ограничена размером стека вызовов
+    pub fn walk(
         &self,
-        package: &str,
+        root: &str,
         exclude_filter: &str,
-        rev: &HashMap<String, Vec<String>>,
-        visited: &mut HashSet<String>,
-        stack: &mut Vec<String>,
-    ) {
-        if let Some(f) = exclude_nonempty(exclude_filter) {
-            if package.contains(f) {
-                return;
+        limits: Limits,
+        direction: Direction,
+        visitor: &mut dyn TreeVisitor,
+    ) -> Truncation {
+        let edge_of = |from: NodeId, to: NodeId| match direction {
+            Direction::Forward => self.edge_info.get(&(from, to)),
+            Direction::Reverse => self.edge_info.get(&(to, from)),
+        };
+
+        let mut walk = Walk::new(self, exclude_filter, limits, direction, visitor);
+        // Корень может отсутствовать в графе: тогда он выводится без потомков
+        let Some(root_id) = self.id(root) else {
+            if walk.enter(root, None, None) {
+                walk.leave(root, None);
+            }
+            return walk.truncation;
+        };
+        // Кадр обхода: узел, его соседи и индекс следующего соседа
+        let mut frames: Vec<(NodeId, &[NodeId], usize)> = Vec::new();
+        if walk.enter(root, Some(root_id), None) {
+            frames.push((root_id, self.neighbors(root_id, direction), 0));
+        }
+        while let Some((package, children, next)) = frames.last_mut() {
+            if *next < children.len() && !walk.budget_exhausted(children.len() - *next) {
+                let child = children[*next];
+                *next += 1;
+                if walk.enter(self.name(child), Some(child), edge_of(*package, child)) {
+                    frames.push((child, self.neighbors(child, direction), 0));
+                }
+            } else {
+                let package = *package;
+                frames.pop();
+                walk.leave(self.name(package), Some(package));
             }
         }
+        walk.truncation
+    }
 
-        if stack.contains(&package.to_string()) {
-            println!("{}{} (cycle)", "  ".repeat(stack.len()), package);
-            return;
+    /// Обход в ширину: узлы по уровням удалённости от корня (каждый узел - на ближайшем уровне)
+    pub fn bfs_levels(
+        &self,
+        root: &str,
+        exclude_filter: &str,
+        limits: Limits,
+        direction: Direction,
+    ) -> (Vec<Vec<String>>, Truncation) {
+        let filter = exclude_nonempty(exclude_filter);
+        let excluded = |name: &str| filter.is_some_and(|f| name.contains(f));
+
+        let mut levels: Vec<Vec<String>> = Vec::new();
+        let mut truncation = Truncation::default();
+        let Some(root) = self.id(root).filter(|&r| !excluded(self.name(r))) else {
+            return (levels, truncation);
+        };
+        let mut seen = vec![false; self.len()];
+        seen[root.index()] = true;
+        let mut queue: VecDeque<(NodeId, usize)> = VecDeque::from([(root, 0)]);
+        let mut printed = 0;
+
+        while let Some((id, depth)) = queue.pop_front() {
+            if limits.nodes_reached(printed) {
+                truncation.node_cut += queue.len() + 1;
+                break;
+            }
+            if levels.len() <= depth {
+                levels.push(Vec::new());
+            }
+            levels[depth].push(self.name(id).to_string());
+            printed += 1;
+
+            let fresh: Vec<NodeId> = self
+                .neighbors(id, direction)
+                .iter()
+                .copied()
+                .filter(|n| !seen[n.index()] && !excluded(self.name(*n)))
+                .collect();
+            if fresh.is_empty() {
+                continue;
+            }
+            if limits.depth_reached(depth) {
+                truncation.depth_cut += 1;
+                continue;
+            }
+            for n in fresh {
+                if !seen[n.index()] {
+                    seen[n.index()] = true;
+                    queue.push_back((n, depth + 1));
+                }
+            }
         }
-        if visited.contains(package) {
-            println!("{}{} (visited)", "  ".repeat(stack.len()), package);
+        (levels, truncation)
+    }
+}
+
+#[cfg(test)]
+impl DependencyGraph {
+    /// Описание графа для сравнения в тестах: по строке на узел (по имени) с версией, атрибутами
+    /// и зависимостями в порядке объявления; ребро без сведений равно ребру со сведениями по умолчанию
+    pub fn describe(&self) -> Vec<String> {
+        self.node_names()
+            .into_iter()
+            .map(|name| {
+                let deps: Vec<String> = self
+                    .dependencies(name)
+                    .map(|dep| match self.edge_info(name, dep).filter(|i| **i != EdgeInfo::default()) {
+                        Some(info) => format!("{} [{}]", dep, info.label()),
+                        None => dep.to_string(),
+                    })
+                    .collect();
+                let attrs = self.attributes(name).filter(|a| !a.is_empty());
+                format!("{} {:?} {:?}: {}", name, self.version(name), attrs, deps.join(", "))
+            })
+            .collect()
+    }
+}
+
+/// Направление обхода графа
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// От пакета к его зависимостям
+    Forward,
+    /// От пакета к зависящим от него пакетам
+    Reverse,
+}
+
+/// Бюджет узлов в режиме полного раскрытия, если MaxNodes не задан
+pub const FULL_EXPANSION_BUDGET: usize = 10_000;
+
+/// Как выводится узел, уже раскрытый в другой ветви дерева
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dedupe {
+    /// Повтор помечается "(visited)"
+    #[default]
+    Marker,
+    /// Повтор помечается ссылкой на место первого вывода
+    Reference,
+    /// Поддерево раскрывается заново на каждом пути (как `cargo tree --no-dedupe`)
+    Off,
+}
+
+impl std::str::FromStr for Dedupe {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "marker" => Ok(Dedupe::Marker),
+            "reference" => Ok(Dedupe::Reference),
+            "off" => Ok(Dedupe::Off),
+            other => Err(format!("unknown dedupe mode '{other}', expected 'marker', 'reference' or 'off'")),
+        }
+    }
+}
+
+/// Ограничения на глубину обхода и количество узлов, режим вывода повторов
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub max_depth: Option<usize>,
+    pub max_nodes: Option<usize>,
+    pub dedupe: Dedupe,
+}
+
+impl Limits {
+    /// Достигнута ли максимальная глубина
+    pub fn depth_reached(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|m| depth >= m)
+    }
+
+    /// Исчерпан ли бюджет узлов
+    pub fn nodes_reached(&self, count: usize) -> bool {
+        self.max_nodes.is_some_and(|m| count >= m)
+    }
+}
+
+/// Сводка об отсечённых ветвях и узлах
+#[derive(Debug, Default)]
+pub struct Truncation {
+    /// Число ветвей, обрезанных по глубине
+    pub depth_cut: usize,
+    /// Число узлов (или ветвей при печати), не попавших в бюджет
+    pub node_cut: usize,
+}
+
+impl Truncation {
+    /// Было ли что-либо отсечено
+    pub fn is_empty(&self) -> bool {
+        self.depth_cut == 0 && self.node_cut == 0
+    }
+
+    /// Вывод сводки об усечении
+    pub fn print_summary(&self, what: &str) {
+        if self.is_empty() {
             return;
         }
+        println!(
+            "\n{} truncated: {} cut by depth limit, {} cut by node limit",
+            what, self.depth_cut, self.node_cut
+        );
+    }
+}
 
-        println!("{}{}", "  ".repeat(stack.len()), package);
-        visited.insert(package.to_string());
-        stack.push(package.to_string());
+/// Место первого вывода узла, на которое ссылается его повтор
+#[derive(Debug, Clone, Copy)]
+pub struct FirstSeen<'a> {
+    /// Порядковый номер узла среди событий `enter` (с нуля)
+    pub index: usize,
+    /// Родитель, под которым узел был раскрыт (None для корня)
+    pub parent: Option<&'a str>,
+}
 
-        if let Some(parents) = rev.get(package) {
-            for p in parents {
-                self.dfs_reverse(p, exclude_filter, rev, visited, stack);
+/// Получатель событий обхода дерева зависимостей. `depth` - глубина узла (у корня 0),
+/// `edge` - ребро, по которому обход пришёл в узел
+pub trait TreeVisitor {
+    /// Вход в узел; за ним следуют события потомков и `leave`
+    fn enter(&mut self, _name: &str, _depth: usize, _edge: Option<&EdgeInfo>) {}
+
+    /// Выход из узла
+    fn leave(&mut self, _name: &str, _depth: usize) {}
+
+    /// Узел уже находится на текущем пути
+    fn cycle(&mut self, _name: &str, _depth: usize, _edge: Option<&EdgeInfo>) {}
+
+    /// Узел уже раскрыт в другой ветви; `first` - место первого вывода (в режиме Reference)
+    fn visited(&mut self, _name: &str, _depth: usize, _edge: Option<&EdgeInfo>, _first: Option<FirstSeen<'_>>) {}
+
+    /// Узел отброшен фильтром
+    fn filtered(&mut self, _name: &str, _depth: usize) {}
+
+    /// Потомки на глубине `depth` отсечены по указанной причине
+    fn cut(&mut self, _depth: usize, _reason: &str) {}
+}
+
+// Состояние обхода: пройденные узлы, текущий путь и бюджет
+struct Walk<'a> {
+    graph: &'a DependencyGraph,
+    exclude_filter: &'a str,
+    limits: Limits,
+    direction: Direction,
+    /// Номер первого вывода узла и родитель, под которым он раскрыт
+    first: Vec<Option<(usize, Option<NodeId>)>>,
+    /// Узлы текущего пути - для проверки циклов за O(1)
+    on_path: Vec<bool>,
+    /// Раскрываемые узлы текущего пути (без корня, отсутствующего в графе)
+    path: Vec<NodeId>,
+    depth: usize,
+    printed: usize,
+    truncation: Truncation,
+    visitor: &'a mut dyn TreeVisitor,
+}
+
+impl<'a> Walk<'a> {
+    fn new(
+        graph: &'a DependencyGraph,
+        exclude_filter: &'a str,
+        mut limits: Limits,
+        direction: Direction,
+        visitor: &'a mut dyn TreeVisitor,
+    ) -> Self {
+        // Полное раскрытие растёт экспоненциально, поэтому без бюджета не выполняется
+        if limits.dedupe == Dedupe::Off {
+            limits.max_nodes.get_or_insert(FULL_EXPANSION_BUDGET);
+        }
+        Self {
+            graph,
+            exclude_filter,
+            limits,
+            direction,
+            first: vec![None; graph.len()],
+            on_path: vec![false; graph.len()],
+            path: Vec::new(),
+            depth: 0,
+            printed: 0,
+            truncation: Truncation::default(),
+            visitor,
+        }
+    }
+
+    // Вход в узел; true, если обход должен продолжиться в его потомков
+    fn enter(&mut self, package: &str, id: Option<NodeId>, edge: Option<&EdgeInfo>) -> bool {
+        let depth = self.depth;
+        if let Some(f) = exclude_nonempty(self.exclude_filter)
+            && package.contains(f)
+        {
+            self.visitor.filtered(package, depth);
+            return false;
+        }
+
+        // Проверка бесконечных циклов
+        if let Some(id) = id {
+            if self.on_path[id.index()] {
+                self.visitor.cycle(package, depth, edge);
+                return false;
+            }
+            match self.first[id.index()] {
+                Some((index, parent)) if self.limits.dedupe != Dedupe::Off => {
+                    let first = (self.limits.dedupe == Dedupe::Reference).then(|| FirstSeen {
+                        index,
+                        parent: parent.map(|p| self.graph.name(p)),
+                    });
+                    self.visitor.visited(package, depth, edge, first);
+                    return false;
+                }
+                Some(_) => {}
+                None => self.first[id.index()] = Some((self.printed, self.path.last().copied())),
             }
         }
 
-        stack.pop();
+        self.visitor.enter(package, depth, edge);
+        self.printed += 1;
+
+        let has_children = id.is_some_and(|id| !self.graph.neighbors(id, self.direction).is_empty());
+        if self.limits.depth_reached(depth) && (self.is_truncated(package) || has_children) {
+            // Узел, отсечённый ещё при построении графа, сохраняет исходную причину
+            let reason = self.graph.truncated.get(package).copied().unwrap_or("depth limit");
+            self.visitor.cut(depth + 1, reason);
+            self.visitor.leave(package, depth);
+            match reason {
+                "node limit" => self.truncation.node_cut += 1,
+                _ => self.truncation.depth_cut += 1,
+            }
+            return false;
+        }
+
+        self.depth += 1;
+        if let Some(id) = id {
+            self.on_path[id.index()] = true;
+            self.path.push(id);
+        }
+        true
+    }
+
+    // Завершение узла: отметка о зависимостях, отсечённых при построении графа
+    fn leave(&mut self, package: &str, id: Option<NodeId>) {
+        if !self.graph.truncated.is_empty()
+            && let Some(reason) = self.graph.truncated.get(package)
+        {
+            self.visitor.cut(self.depth, reason);
+        }
+        self.depth -= 1;
+        if let Some(id) = id {
+            self.on_path[id.index()] = false;
+            self.path.pop();
+        }
+        self.visitor.leave(package, self.depth);
+    }
+
+    // Отсечены ли зависимости узла при построении графа
+    fn is_truncated(&self, package: &str) -> bool {
+        !self.graph.truncated.is_empty() && self.graph.truncated.contains_key(package)
+    }
+
+    // Проверка бюджета узлов перед выводом очередного потомка;
+    // `remaining` - число ещё не выведенных потомков текущего узла
+    fn budget_exhausted(&mut self, remaining: usize) -> bool {
+        if !self.limits.nodes_reached(self.printed) {
+            return false;
+        }
+        if self.truncation.node_cut == 0 {
+            self.visitor.cut(self.depth, "node limit");
+        }
+        self.truncation.node_cut += remaining;
+        true
     }
 }
 
@@ -157,3 +783,52 @@ fn exclude_nonempty(s: &str) -> Option<&str> {
     let t = s.trim();
     if t.is_empty() { None } else { Some(t) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::TreeStyle;
+
+    fn graph(edges: &[(&str, &str)]) -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in edges {
+            g.add_edge(from, to);
+        }
+        g
+    }
+
+    fn tree(graph: &DependencyGraph, root: &str, limits: Limits) -> (String, Truncation) {
+        let style = TextStyle { tree: TreeStyle::Indent, depth_prefix: false, color: false, width: None };
+        graph.render_tree(root, "", limits, Direction::Forward, style)
+    }
+
+    #[test]
+    fn restrict_does_not_spend_budget_on_excluded_packages() {
+        let g = graph(&[("R", "tmp-a"), ("R", "tmp-b"), ("R", "A"), ("R", "B"), ("tmp-a", "C"), ("A", "D")]);
+        let limits = Limits { max_nodes: Some(3), ..Limits::default() };
+        let (limited, truncation) = g.restrict("R", "tmp", Direction::Forward, limits);
+        // Отфильтрованные пакеты остаются листьями и не тянут за собой свои зависимости
+        assert_eq!(limited.node_names(), ["A", "B", "R", "tmp-a", "tmp-b"]);
+        assert!(limited.dependencies("tmp-a").next().is_none());
+        assert_eq!(truncation.node_cut, 1);
+        assert_eq!(limited.truncated.get("A"), Some(&"node limit"));
+
+        // Без фильтра те же пакеты расходуют бюджет
+        let (limited, _) = g.restrict("R", "", Direction::Forward, limits);
+        assert_eq!(limited.node_names(), ["R", "tmp-a", "tmp-b"]);
+    }
+
+    #[test]
+    fn walk_keeps_the_original_cut_reason() {
+        let g = graph(&[("R", "A"), ("A", "B"), ("A", "C")]);
+        let (limited, _) = g.restrict("R", "", Direction::Forward, Limits { max_nodes: Some(2), ..Limits::default() });
+        assert_eq!(limited.truncated.get("A"), Some(&"node limit"));
+
+        let depth = Limits { max_depth: Some(1), ..Limits::default() };
+        let (text, truncation) = tree(&limited, "R", depth);
+        assert_eq!(text, "R\n  A\n    … (node limit)\n");
+        assert_eq!((truncation.depth_cut, truncation.node_cut), (0, 1));
+        // Узел с потомками в самом графе отсекается по глубине
+        assert_eq!(tree(&g, "R", depth).0, "R\n  A\n    … (depth limit)\n");
+    }
+}
@@ -0,0 +1,528 @@
+// Модуль для формирования JSON без сторонних библиотек
+
+use std::fmt::Write;
+
+/// Значение JSON
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Объект с сохранением порядка полей
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Объект из списка пар "ключ - значение"
+    pub fn object<K: Into<String>>(fields: Vec<(K, JsonValue)>) -> Self {
+        JsonValue::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Массив строк
+    pub fn strings<S: AsRef<str>>(items: impl IntoIterator<Item = S>) -> Self {
+        JsonValue::Array(items.into_iter().map(|s| JsonValue::String(s.as_ref().to_string())).collect())
+    }
+
+    /// Сериализация с отступами в два пробела
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            JsonValue::Array(items) if items.iter().all(is_scalar) => {
+                // Короткие массивы скаляров пишутся в одну строку
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write_pretty(out, indent);
+                }
+                out.push(']');
+            }
+            JsonValue::Array(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&pad);
+                    item.write_pretty(out, indent + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push(']');
+            }
+            JsonValue::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            JsonValue::Object(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&pad);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write_pretty(out, indent + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+            }
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            JsonValue::Number(n) => write_number(out, *n),
+            JsonValue::String(s) => write_string(out, s),
+        }
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        JsonValue::String(s.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        JsonValue::String(s)
+    }
+}
+
+impl From<usize> for JsonValue {
+    fn from(n: usize) -> Self {
+        JsonValue::Number(n as f64)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Bool(b)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(v: Option<T>) -> Self {
+        v.map(Into::into).unwrap_or(JsonValue::Null)
+    }
+}
+
+fn is_scalar(v: &JsonValue) -> bool {
+    !matches!(v, JsonValue::Array(_) | JsonValue::Object(_))
+}
+
+// Целые числа выводятся без дробной части
+fn write_number(out: &mut String, n: f64) {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        let _ = write!(out, "{}", n as i64);
+    } else if n.is_finite() {
+        let _ = write!(out, "{}", n);
+    } else {
+        out.push_str("null");
+    }
+}
+
+// Строка с экранированием спецсимволов
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Доступ к значениям при разборе
+impl JsonValue {
+    /// Поле объекта по ключу
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Строковое значение
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Числовое значение
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Логическое значение
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Элементы массива
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Проверка на null
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+}
+
+/// Разбор JSON-документа; ошибка содержит позицию (строка:столбец)
+pub fn parse(text: &str) -> Result<JsonValue, String> {
+    let mut p = Parser { chars: text.chars().collect(), pos: 0, depth: 0 };
+    let value = p.value()?;
+    p.skip_ws();
+    if p.pos < p.chars.len() {
+        return Err(p.error("unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+/// Наибольшая вложенность массивов и объектов: разбор рекурсивный, и без предела
+/// документ вида `[[[[...` переполнил бы стек
+const MAX_DEPTH: usize = 256;
+
+// Рекурсивный разбор по символам
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// Текущая вложенность массивов и объектов
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, msg: &str) -> String {
+        let before = &self.chars[..self.pos.min(self.chars.len())];
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let col = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        format!("{} at {}:{}", msg, line, col)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, ch: char) -> Result<(), String> {
+        self.skip_ws();
+        if self.chars.get(self.pos) == Some(&ch) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", ch)))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, String> {
+        let end = self.pos + word.chars().count();
+        if end <= self.chars.len() && self.chars[self.pos..end].iter().copied().eq(word.chars()) {
+            self.pos = end;
+            Ok(value)
+        } else {
+            Err(self.error("unexpected token"))
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_ws();
+        match self.chars.get(self.pos) {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => self.string().map(JsonValue::String),
+            Some('t') => self.keyword("true", JsonValue::Bool(true)),
+            Some('f') => self.keyword("false", JsonValue::Bool(false)),
+            Some('n') => self.keyword("null", JsonValue::Null),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    // Разбор вложенного массива или объекта с проверкой глубины
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<JsonValue, String>) -> Result<JsonValue, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(&format!("nesting deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_ws();
+        if self.chars.get(self.pos) == Some(&'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            self.skip_ws();
+            if self.chars.get(self.pos) != Some(&'"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_ws();
+            match self.chars.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_ws();
+        if self.chars.get(self.pos) == Some(&']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.chars.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(&c) = self.chars.get(self.pos) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let Some(&esc) = self.chars.get(self.pos) else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.pos += 1;
+                    match esc {
+                        '"' | '\\' | '/' => out.push(esc),
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'u' => out.push(self.unicode_escape()?),
+                        _ => return Err(self.error("invalid escape sequence")),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    // \uXXXX, включая суррогатные пары
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if (0xD800..0xDC00).contains(&high) {
+            if self.chars.get(self.pos) == Some(&'\\') && self.chars.get(self.pos + 1) == Some(&'u') {
+                self.pos += 2;
+                let low = self.hex4()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("invalid surrogate pair"));
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return char::from_u32(code).ok_or_else(|| self.error("invalid surrogate pair"));
+            }
+            return Err(self.error("unpaired surrogate"));
+        }
+        char::from_u32(high).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let end = self.pos + 4;
+        if end > self.chars.len() {
+            return Err(self.error("invalid unicode escape"));
+        }
+        // from_str_radix допускает знак, поэтому цифры проверяются отдельно
+        if !self.chars[self.pos..end].iter().all(char::is_ascii_hexdigit) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        let hex: String = self.chars[self.pos..end].iter().collect();
+        let code = u32::from_str_radix(&hex, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos = end;
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        if !is_json_number(&text) {
+            return Err(self.error(&format!("invalid number '{}'", text)));
+        }
+        text.parse::<f64>()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error(&format!("invalid number '{}'", text)))
+    }
+}
+
+// Число по грамматике JSON: -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+// (f64::from_str принимает и "1.", ".5", "+1", "01")
+fn is_json_number(text: &str) -> bool {
+    fn digits(s: &str) -> (&str, &str) {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        s.split_at(end)
+    }
+    let s = text.strip_prefix('-').unwrap_or(text);
+    let (int, mut rest) = digits(s);
+    if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+        return false;
+    }
+    if let Some(frac) = rest.strip_prefix('.') {
+        let (d, r) = digits(frac);
+        if d.is_empty() {
+            return false;
+        }
+        rest = r;
+    }
+    if let Some(exp) = rest.strip_prefix(['e', 'E']) {
+        let (d, r) = digits(exp.strip_prefix(['+', '-']).unwrap_or(exp));
+        if d.is_empty() {
+            return false;
+        }
+        rest = r;
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_escapes() {
+        let v = parse(r#""a\"b\\c\/d\n\r\t\b\f\u0041\u00e9""#).unwrap();
+        assert_eq!(v.as_str(), Some("a\"b\\c/d\n\r\t\u{8}\u{c}Aé"));
+    }
+
+    #[test]
+    fn parses_surrogate_pairs() {
+        assert_eq!(parse(r#""\ud83d\ude00""#).unwrap().as_str(), Some("\u{1f600}"));
+        assert_eq!(parse(r#""\uD834\uDD1E""#).unwrap().as_str(), Some("\u{1d11e}"));
+    }
+
+    #[test]
+    fn rejects_invalid_surrogates() {
+        // Вторая половина пары вне DC00-DFFF
+        assert!(parse(r#""\ud83d\u0041""#).unwrap_err().starts_with("invalid surrogate pair"));
+        assert!(parse(r#""\ud83d\ud83d""#).unwrap_err().starts_with("invalid surrogate pair"));
+        assert!(parse(r#""\ud83d""#).unwrap_err().starts_with("unpaired surrogate"));
+        assert!(parse(r#""\ude00""#).unwrap_err().starts_with("invalid unicode escape"));
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        assert!(parse(r#""\x""#).unwrap_err().starts_with("invalid escape sequence"));
+        assert!(parse(r#""\u12""#).unwrap_err().starts_with("invalid unicode escape"));
+        assert!(parse(r#""\u+123""#).unwrap_err().starts_with("invalid unicode escape"));
+        assert!(parse(r#""abc"#).unwrap_err().starts_with("unterminated string"));
+    }
+
+    #[test]
+    fn parses_numbers() {
+        for (text, n) in [("0", 0.0), ("-0", 0.0), ("42", 42.0), ("-17", -17.0), ("3.25", 3.25), ("1e3", 1000.0)] {
+            assert_eq!(parse(text).unwrap(), JsonValue::Number(n), "{}", text);
+        }
+        assert_eq!(parse("2.5E-2").unwrap(), JsonValue::Number(0.025));
+        assert_eq!(parse("-1.5e+2").unwrap(), JsonValue::Number(-150.0));
+    }
+
+    #[test]
+    fn rejects_invalid_numbers() {
+        for text in ["01", "1.", "-", "1e", "1e+", "--1", "1.2.3", "-.5", "1ee2"] {
+            assert!(parse(text).unwrap_err().starts_with("invalid number"), "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_nesting() {
+        let v = parse(r#" { "a": [1, {"b": null}, []], "c": {"d": [true, false]}, "e": {} } "#).unwrap();
+        let a = v.get("a").and_then(JsonValue::as_array).unwrap();
+        assert_eq!(a.len(), 3);
+        assert!(a[1].get("b").unwrap().is_null());
+        assert_eq!(a[2], JsonValue::Array(Vec::new()));
+        let d = v.get("c").and_then(|c| c.get("d")).and_then(JsonValue::as_array).unwrap();
+        assert_eq!(d, [JsonValue::Bool(true), JsonValue::Bool(false)]);
+        assert_eq!(v.get("e"), Some(&JsonValue::Object(Vec::new())));
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        assert_eq!(parse("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err(), "expected ':' at 3:7");
+        assert_eq!(parse("[1, 2").unwrap_err(), "expected ',' or ']' at 1:6");
+        assert_eq!(parse("[1] x").unwrap_err(), "unexpected trailing characters at 1:5");
+        assert_eq!(parse("").unwrap_err(), "unexpected end of input at 1:1");
+        assert_eq!(parse("{\"a\": tru}").unwrap_err(), "unexpected token at 1:7");
+    }
+
+    #[test]
+    fn round_trips_pretty_output() {
+        let value = JsonValue::object(vec![
+            ("name", "quote \" backslash \\ tab \t bell \u{7} 😀".into()),
+            ("count", 3usize.into()),
+            ("ratio", JsonValue::Number(0.125)),
+            ("missing", JsonValue::Null),
+            ("flags", JsonValue::Array(vec![true.into(), false.into()])),
+            ("items", JsonValue::Array(vec![JsonValue::object(vec![("x", JsonValue::strings(["a", "b"]))])])),
+            ("empty", JsonValue::object(Vec::<(&str, JsonValue)>::new())),
+        ]);
+        assert_eq!(parse(&value.to_pretty_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)).unwrap_err(), "nesting deeper than 256 levels at 1:257");
+        assert!(parse(&"[{\"a\": ".repeat(100_000)).unwrap_err().starts_with("nesting deeper than 256 levels"));
+    }
+}
@@ -0,0 +1,162 @@
+// Модуль для разбора Cargo.lock и построения полного графа зависимостей
+
+use std::collections::HashMap;
+use std::fs;
+
+use thiserror::Error;
+
+/// Перечисление возможных ошибок при работе с Cargo.lock
+#[derive(Debug, Error)]
+pub enum LockfileError {
+    #[error("cannot read Cargo.lock: {0}")]
+    Read(String),
+
+    #[error("invalid Cargo.lock format at line {line}: {msg}")]
+    Parse { line: usize, msg: String },
+}
+
+/// Запись о пакете из секции [[package]]
+#[derive(Debug, Clone)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// Источник пакета (реестр или git); отсутствует у пакетов рабочего пространства
+    pub source: Option<String>,
+    /// Ссылки на зависимости в формате Cargo.lock: "name" или "name version"
+    pub dependencies: Vec<String>,
+}
+
+/// Загрузка и разбор Cargo.lock (допускается путь к каталогу пакета)
+pub fn load_lockfile(path: &str) -> Result<Vec<LockedPackage>, LockfileError> {
+    let p = std::path::Path::new(path);
+    let p = if p.is_dir() { p.join("Cargo.lock") } else { p.to_path_buf() };
+    let content = fs::read_to_string(&p)
+        .map_err(|e| LockfileError::Read(format!("{}: {}", p.display(), e)))?;
+    parse_lockfile(&content)
+}
+
+/// Разбор содержимого Cargo.lock
+pub fn parse_lockfile(content: &str) -> Result<Vec<LockedPackage>, LockfileError> {
+    let mut packages = Vec::new();
+    let mut current: Option<LockedPackage> = None;
+    // Признак того, что читается многострочный массив dependencies = [ ... ]
+    let mut in_deps = false;
+
+    for (idx, line) in content.lines().enumerate() {
+        let line_no = idx + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if in_deps {
+            if trimmed.starts_with(']') {
+                in_deps = false;
+            } else if let Some(pkg) = current.as_mut() {
+                pkg.dependencies.push(unquote(trimmed.trim_end_matches(','), line_no)?);
+            }
+            continue;
+        }
+
+        if trimmed.starts_with('[') {
+            if let Some(pkg) = current.take() {
+                packages.push(finish(pkg, line_no)?);
+            }
+            if trimmed == "[[package]]" {
+                current = Some(LockedPackage {
+                    name: String::new(),
+                    version: String::new(),
+                    source: None,
+                    dependencies: Vec::new(),
+                });
+            }
+            continue;
+        }
+
+        // Поля вне секции [[package]] (version, [metadata]) игнорируются
+        let Some(pkg) = current.as_mut() else { continue };
+        let Some((key, value)) = trimmed.split_once('=') else {
+            return Err(LockfileError::Parse { line: line_no, msg: format!("expected 'key = value', got '{}'", trimmed) });
+        };
+
+        match key.trim() {
+            "name" => pkg.name = unquote(value.trim(), line_no)?,
+            "version" => pkg.version = unquote(value.trim(), line_no)?,
+            "source" => pkg.source = Some(unquote(value.trim(), line_no)?),
+            "dependencies" => {
+                let value = value.trim();
+                if value == "[" {
+                    in_deps = true;
+                } else {
+                    // Однострочная форма: dependencies = ["a", "b"]
+                    let inner = value.trim_start_matches('[').trim_end_matches(']');
+                    for item in inner.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        pkg.dependencies.push(unquote(item, line_no)?);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if in_deps {
+        return Err(LockfileError::Parse { line: content.lines().count(), msg: "unterminated dependencies array".into() });
+    }
+    if let Some(pkg) = current.take() {
+        packages.push(finish(pkg, content.lines().count())?);
+    }
+
+    Ok(packages)
+}
+
+/// Построение отображения "ссылка из Cargo.lock" -> имя узла графа.
+/// Пакеты с единственной версией называются просто по имени,
+/// при нескольких версиях используется форма `name@version`.
+pub fn node_names(packages: &[LockedPackage]) -> HashMap<String, String> {
+    let mut versions: HashMap<&str, usize> = HashMap::new();
+    for p in packages {
+        *versions.entry(p.name.as_str()).or_default() += 1;
+    }
+
+    let mut names = HashMap::new();
+    for p in packages {
+        let node = if versions[p.name.as_str()] > 1 {
+            format!("{}@{}", p.name, p.version)
+        } else {
+            p.name.clone()
+        };
+        if versions[p.name.as_str()] == 1 {
+            names.insert(p.name.clone(), node.clone());
+        }
+        names.insert(format!("{} {}", p.name, p.version), node);
+    }
+    names
+}
+
+/// Разрешение ссылки на зависимость ("name", "name version" или "name version (source)")
+pub fn resolve_reference<'a>(names: &'a HashMap<String, String>, reference: &str) -> Option<&'a str> {
+    let mut parts = reference.split_whitespace();
+    let name = parts.next()?;
+    match parts.next() {
+        Some(version) => names.get(&format!("{} {}", name, version)),
+        None => names.get(name),
+    }
+    .map(String::as_str)
+}
+
+// Проверка обязательных полей пакета
+fn finish(pkg: LockedPackage, line: usize) -> Result<LockedPackage, LockfileError> {
+    if pkg.name.is_empty() {
+        return Err(LockfileError::Parse { line, msg: "package without name".into() });
+    }
+    Ok(pkg)
+}
+
+// Снятие кавычек со строкового значения TOML
+fn unquote(value: &str, line: usize) -> Result<String, LockfileError> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(str::to_string)
+        .ok_or_else(|| LockfileError::Parse { line, msg: format!("expected quoted string, got '{}'", value) })
+}
@@ -6,184 +6,370 @@
 // дерева зависимостей и d2 диаграммы
 //    cargo run -- <config.xml> --reverse --d2 deps.d2 --render deps.png --open - вывод дерева
 // обратных зависимостей и d2 диаграммы
+//    cargo run -- ./config.example.xml --max-depth 2 --max-nodes 50 - ограничение глубины
+// обхода и числа узлов
+//    cargo run -- ./config.example.xml cycles --max-cycle-len 4 - компоненты сильной связности
+// и элементарные циклы
+//    cargo run -- ./config.example.xml order --json - порядок сборки и уровни параллельной сборки
+//    cargo run -- ./config.example.xml why D --shortest 3 - пути от корня до пакета D
+//    cargo run -- ./config.example.xml stats --sort tdeps - метрики узлов графа
+//    cargo run -- ./config.example.xml diff other.xml --exit-code - сравнение двух графов
+//    cargo run -- ./config.example.xml impact B --d2 what_if.d2 - пакеты, которые исчезнут
+// вместе с B, и граф без него
+//    cargo run -- ./config.example.xml --condense - дерево и D2 с компонентами, свёрнутыми в узлы
+//    cargo run -- ./config.example.xml query "deps(A, 2) - rdeps(D)" --d2 q.d2 - запрос к графу
+// и экспорт найденного подграфа
+//    cargo run -- ./config.example.xml --no-dedupe --max-nodes 500 - дерево с повторным раскрытием
+// поддеревьев на каждом пути
+//    cargo run -- ./config.example.xml --tree-style unicode --depth-prefix - дерево с направляющими
+// и номерами глубины (цвет: --color auto|always|never, обрезка строк: --width)
+//    cargo run -- ./config.example.xml --strict - строгая проверка тестового репозитория
+//    cargo run -- ./config.example.xml --d2 deps.d2 --d2-theme dark - D2 с заголовком, легендой
+// и классами для корня, глубины, циклов и типов рёбер
+//    cargo run -- ./config.example.xml --d2 all.d2 --d2-whole-graph - D2 со всем графом, а не только
+// с деревом от корня
+//    cargo run -- ./config.example.xml --child-order size - потомки по числу транзитивных зависимостей
+//    cargo run -- ./config.example.xml --bfs - узлы по уровням обхода в ширину
+//    cargo run --release --features bench -- bench 100000 - замер построения, памяти и обходов
+// на сгенерированных графах из 100000 узлов
+//    cargo run -- generate random gen.txt --nodes 50 --density 2 --cycles 3
+// --max-depth 6 --seed 7 - тестовый репозиторий заданной формы (также chain, diamond, star,
+// self-loop, components)
+//    cargo run -- ./config.example.xml --save-snapshot graph.json - сохранение графа в JSON-снимок
+//    cargo run -- ./config.example.xml --load-snapshot graph.json stats - анализ графа из снимка
+// без обращения к источнику
+//    cargo run -- ./config.example.xml --save-test-repo fixture.txt --anonymize - граф в формате
+// тестового репозитория с именами A, B, C, ...
+//    cargo run -- ./config.example.xml --reduce --collapse-chains --d2 deps.d2 - дерево и D2
+// без транзитивных рёбер и с линейными цепочками, свёрнутыми в одно ребро
 
+#[cfg(feature = "bench")]
+mod bench;
+mod child_order;
+mod cli;
+mod commands;
 mod config;
+mod generate;
 mod cargo_parser;
 mod graph;
 mod test_repo;
+mod lockfile;
+mod source;
+mod scc;
+mod order;
+mod json;
+mod why;
+mod stats;
+mod diff;
+mod dominators;
 mod d2;
+mod output;
+mod render;
+mod query;
+mod snapshot;
+mod transform;
 
 use std::env;
+use std::fs;
 
+use cli::Command;
 use config::{AppConfig, ConfigError};
-use cargo_parser::get_dependencies;
-use graph::DependencyGraph;
-use test_repo::load_test_repo;
-use std::fs;
-use which::which;
+use graph::{Dedupe, Direction, FULL_EXPANSION_BUDGET};
+use render::TextStyle;
+use source::{source_for, SourceError};
 use crate::cargo_parser::CargoParseError;
+use crate::lockfile::LockfileError;
+use crate::snapshot::SnapshotError;
 
 
 /// Точка входа в приложение: загружает конфигурацию и извлекает зависимости пакета
 fn main() {
     // Чтение аргументов командной строки
-    let args: Vec<String> = env::args().collect();
-    let mut d2_path: Option<String> = None;
-    let mut render_path: Option<String> = None;
-    let mut open_after_render = false;
-    // Парсер флагов
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--d2" => {
-                if i + 1 < args.len() {
-                    d2_path = Some(args[i + 1].clone());
-                    i += 1;
-                } else {
-                    eprintln!("ERROR: --d2 requires a file path");
-                    return;
-                }
-            }
-            "--render" => {
-                if i + 1 < args.len() {
-                    render_path = Some(args[i + 1].clone());
-                    i += 1;
-                } else {
-                    eprintln!("ERROR: --render requires a file path (e.g. output.svg)");
-                    return;
-                }
-            }
-            "--open" => {
-                open_after_render = true;
-            }
-            _ => {}
+    let args: Vec<String> = env::args().skip(1).collect();
+    let cli = match cli::parse_args(&args) {
+        Ok(c) => c,
+        Err(msg) => {
+            eprintln!("ERROR: {}", msg);
+            return failed(args.iter().any(|a| a == "--exit-code"));
         }
-        i += 1;
+    };
+    let reverse = cli.outputs.reverse;
+
+    // Замер производительности не требует конфигурации
+    if let Command::Bench(size) = &cli.command {
+        run_bench(size.as_deref());
+        return;
     }
 
-    let config_path = args.get(1).cloned().unwrap_or_else(|| "config.example.xml".to_string());
-    let reverse = args.iter().any(|s| s == "--reverse");
+    // Генерация тестового репозитория тоже выполняется без конфигурации
+    if let Command::Generate(shape, path) = &cli.command {
+        match cli.generate_params(shape) {
+            Ok(params) => run_generate(&params, path.as_deref()),
+            Err(e) => print_config_error(e),
+        }
+        return;
+    }
 
     // Загружаем конфиг
-    let cfg = match AppConfig::load_from_file(&config_path) {
+    let mut cfg = match AppConfig::load_from_file(&cli.config_path) {
         Ok(c) => c,
         Err(e) => {
             print_config_error(e);
-            return;
+            return failed(cli.exit_code);
         }
     };
 
-    println!("Config was uploaded successfully");
-    println!("{:#?}", cfg);
-
-    // Создание графа зависимостей
-    let mut graph = DependencyGraph::new();
-
-    if cfg.mode == "test" {
-        // Режим тестового репозитория (Этапы 2–4)
-        println!("\nRunning in TEST mode (text file parsing)");
-        match load_test_repo(&cfg.repo_source) {
-            Ok(map) => {
-                // Загружаем полный граф из файла
-                graph.load_from_map(&map);
-
-                if reverse {
-                    println!("\nReverse dependencies for '{}' ", cfg.package_name);
-                    graph.print_reverse_tree(&cfg.package_name, &cfg.exclude_filter);
-                } else {
-                    println!("\nDependencies for '{}' ", cfg.package_name);
-                    graph.print_tree(&cfg.package_name, &cfg.exclude_filter);
-                }
+    // Флаги командной строки переопределяют параметры из конфига
+    if let Err(e) = cli.apply_to(&mut cfg) {
+        print_config_error(e);
+        return failed(cli.exit_code);
+    }
+
+    // При выводе в JSON служебные сообщения не печатаются, чтобы результат можно было разобрать
+    if !cli.json {
+        println!("Config was uploaded successfully");
+        println!("{:#?}", cfg);
+    }
+
+    // Построение графа зависимостей выбранным источником либо загрузка из снимка;
+    // корень, режим и источник при этом берутся из снимка
+    let (mut graph, description, full_graph) = match &cli.load_snapshot {
+        Some(path) => match snapshot::load(path) {
+            Ok(s) => {
+                cfg.package_name = s.config.package_name;
+                cfg.repo_source = s.config.repo_source;
+                cfg.mode = s.config.mode;
+                (s.graph, s.description, s.full_graph)
             }
             Err(e) => {
-                print_test_repo_error(e);
-                return;
+                print_snapshot_error(e);
+                return failed(cli.exit_code);
             }
-        }
-    } else {
-        // Нормальный режим: прямые зависимости корневого пакета
-        println!("\nRunning in NORMAL mode (Cargo.toml)");
-        match get_dependencies(&cfg.repo_source) {
-            Ok(deps) => {
-                // Создание небольшого графа: корень -> каждая зависимость
-                for d in deps {
-                    graph.add_edge(&cfg.package_name, &d);
+        },
+        None => {
+            let source = source_for(cfg.mode);
+            match source.load(&cfg) {
+                Ok(g) => (g, source.description().to_string(), source.is_full_graph()),
+                Err(e) => {
+                    print_source_error(e);
+                    return failed(cli.exit_code);
                 }
+            }
+        }
+    };
+    if !cli.json {
+        match &cli.load_snapshot {
+            Some(path) => println!("\nRunning on snapshot {} ({})", path, description),
+            None => println!("\nRunning in {}", description),
+        }
+    }
 
-                if reverse {
-                    println!("\nNOTE: reverse mode is only meaningful with a full graph.");
-                    println!("Showing who depends on '{}' among the known nodes only:\n", cfg.package_name);
-                    graph.print_reverse_tree(&cfg.package_name, &cfg.exclude_filter);
-                } else if cfg.ascii_tree {
-                    println!("\nDependencies for '{}' ", cfg.package_name);
-                    graph.print_tree(&cfg.package_name, &cfg.exclude_filter);
-                } else {
-                    println!("\nDirect package dependencies '{}':", cfg.package_name);
-                    for dep in graph.nodes.get(&cfg.package_name).map(|n| &n.dependencies).unwrap_or(&Vec::new()) {
-                        println!("- {}", dep);
-                    }
+    // Снимок и тестовый репозиторий сохраняются до применения ограничений, чтобы граф можно было
+    // анализировать заново
+    if let Some(path) = &cli.save_snapshot {
+        match snapshot::save(path, &graph, &cfg, &description, full_graph) {
+            Ok(()) => {
+                if !cli.json {
+                    println!("Snapshot saved to {}", path);
                 }
             }
             Err(e) => {
-                print_cargo_error(e);
-                return;
+                print_snapshot_error(e);
+                return failed(cli.exit_code);
             }
         }
     }
-
-    // Экспорт в D2 и рендер изображения
-    if d2_path.is_some() || render_path.is_some() {
-        let d2_text = d2::to_d2(&graph, reverse);
-        if let Some(path) = &d2_path {
-            if let Err(e) = fs::write(path, &d2_text) {
-                eprintln!("D2 ERROR: cannot write {}: {}", path, e);
-                return;
-            } else {
-                println!("D2 saved to {}", path);
+    if let Some(path) = &cli.save_test_repo {
+        match test_repo::save(path, &graph, &cfg.package_name, cli.anonymize) {
+            Ok(root) => {
+                if !cli.json {
+                    println!("Test repository saved to {} (root: {})", path, root);
+                }
             }
-        } else {
-            let default_path = "graph.d2";
-            if let Err(e) = fs::write(default_path, &d2_text) {
-                eprintln!("D2 ERROR: cannot write {}: {}", default_path, e);
-                return;
-            } else {
-                println!("D2 saved to {}", default_path);
-                d2_path = Some(default_path.to_string());
+            Err(e) => {
+                print_test_repo_error(e);
+                return failed(cli.exit_code);
             }
         }
+    }
+
+    // Порядок потомков задаётся один раз: подграфы и ограничения его сохраняют
+    child_order::apply(&mut graph, cfg.child_order);
+
+    // Применение ограничений глубины и числа узлов к построенному графу
+    let limits = cfg.limits();
+    if limits.max_depth.is_some() || limits.max_nodes.is_some() {
+        let direction = if reverse { Direction::Reverse } else { Direction::Forward };
+        let (limited, truncation) = graph.restrict(&cfg.package_name, &cfg.exclude_filter, direction, limits);
+        graph = limited;
+        // Дерево выводит собственную сводку о тех же отсечениях, а JSON не смешивается с текстом
+        if cli.command != Command::Tree && !cli.json {
+            truncation.print_summary("Graph");
+        }
+    }
 
-        if let Some(out_img) = &render_path {
-            match which("d2") {
-                Ok(bin) => {
-                    let input = d2_path.as_ref().unwrap();
-                    println!("Rendering with {:?}: {} -> {}", bin, input, out_img);
-                    let status = std::process::Command::new(bin)
-                        .arg(input)
-                        .arg(out_img)
-                        .status();
-
-                    match status {
-                        Ok(s) if s.success() => {
-                            println!("Rendered image: {}", out_img);
-                            if open_after_render {
-                                if let Err(e) = open::that(out_img) {
-                                    eprintln!("OPEN WARN: cannot open {}: {}", out_img, e);
-                                }
-                            }
-                        }
-                        Ok(s) => {
-                            eprintln!("D2 RENDER ERROR: exit code {:?}", s.code());
-                        }
-                        Err(e) => {
-                            eprintln!("D2 RENDER ERROR: {}", e);
-                        }
-                    }
+    if cli.command == Command::Cycles {
+        let max_len = match cli.max_cycle_len.as_deref().map(|v| config::parse_limit("MaxCycleLen", v, 1)) {
+            Some(Err(e)) => return print_config_error(e),
+            Some(Ok(v)) => Some(v),
+            None => None,
+        };
+        commands::run_cycles(&graph, &cfg, max_len);
+        return;
+    }
+    if cli.command == Command::Order {
+        commands::run_order(&graph, &cfg, cli.json);
+        return;
+    }
+    if let Command::Why(target) = &cli.command {
+        let k = match cli.shortest.as_deref().map(|v| config::parse_limit("Shortest", v, 1)) {
+            Some(Err(e)) => return print_config_error(e),
+            Some(Ok(v)) => Some(v),
+            None => None,
+        };
+        commands::run_why(&graph, &cfg, target, k, cli.json);
+        return;
+    }
+    if let Command::Diff(other_path) = &cli.command {
+        // Второй граф берётся из JSON-снимка или строится по другой конфигурации
+        let (other, other_cfg) = if other_path.ends_with(".json") {
+            match snapshot::load(other_path) {
+                Ok(s) => (s.graph, s.config),
+                Err(e) => {
+                    print_snapshot_error(e);
+                    return failed(cli.exit_code);
                 }
-                Err(_) => {
-                    eprintln!("D2 RENDER SKIPPED: 'd2' CLI not found in PATH.");
+            }
+        } else {
+            let other_cfg = match AppConfig::load_from_file(other_path) {
+                Ok(c) => c,
+                Err(e) => {
+                    print_config_error(e);
+                    return failed(cli.exit_code);
+                }
+            };
+            match source_for(other_cfg.mode).load(&other_cfg) {
+                Ok(g) => (g, other_cfg),
+                Err(e) => {
+                    print_source_error(e);
+                    return failed(cli.exit_code);
                 }
             }
+        };
+        // Второй граф нормализуется так же, как первый: фильтр, ограничения и преобразования
+        // берутся из основной конфигурации с флагами, иначе различия в них выглядели бы как
+        // изменения графа. От второй конфигурации или снимка остаётся только корень
+        let other_cfg = AppConfig {
+            exclude_filter: cfg.exclude_filter.clone(),
+            max_depth: cfg.max_depth,
+            max_nodes: cfg.max_nodes,
+            dedupe: cfg.dedupe,
+            transforms: cfg.transforms.clone(),
+            ..other_cfg
+        };
+        let other_limits = other_cfg.limits();
+        let other = if other_limits.max_depth.is_some() || other_limits.max_nodes.is_some() {
+            let direction = if reverse { Direction::Reverse } else { Direction::Forward };
+            other.restrict(&other_cfg.package_name, &other_cfg.exclude_filter, direction, other_limits).0
+        } else {
+            other
+        };
+        let changed = commands::run_diff(&graph, &other, &other_cfg.package_name, &cfg, cli.json);
+        if changed && cli.exit_code {
+            std::process::exit(1);
         }
+        return;
+    }
+    if cli.command == Command::Dominators {
+        commands::run_dominators(&graph, &cfg);
+        return;
+    }
+    if let Command::Impact(package) = &cli.command {
+        // Выходные файлы формируются по графу без удалённого пакета
+        if let Some(what_if) = commands::run_impact(&graph, &cfg, package, cli.json) {
+            let mut outputs = cfg.outputs.clone();
+            output::apply_overrides(&mut outputs, &cli.outputs);
+            output::write_outputs(&what_if, &cfg, &outputs);
+        }
+        return;
+    }
+    if let Command::Query(expr) = &cli.command {
+        // Выходные файлы формируются по подграфу из найденных пакетов; D2 показывает его целиком,
+        // так как корень может в него не входить
+        if let Some(result) = commands::run_query(&graph, &cfg, expr, cli.json) {
+            let mut outputs = cfg.outputs.clone();
+            output::apply_overrides(&mut outputs, &cli.outputs);
+            outputs.iter_mut().for_each(|o| o.whole_graph = o.format == output::OutputFormat::D2);
+            output::write_outputs(&result, &cfg, &outputs);
+        }
+        return;
+    }
+    if cli.command == Command::Stats {
+        let sort = match cli.sort.as_deref().unwrap_or("name").parse() {
+            Ok(s) => s,
+            Err(msg) => return print_config_error(ConfigError::InvalidValue { field: "sort", msg }),
+        };
+        commands::run_stats(&graph, &cfg, sort, cli.json);
+        return;
+    }
+
+    // Преобразования графа (конденсация, редукция, сворачивание цепочек) действуют
+    // на все форматы вывода; при конденсации корень заменяется узлом своей компоненты
+    if !cfg.transforms.is_empty() {
+        let (transformed, root) = transform::apply(&graph, &cfg.package_name, &cfg.transforms);
+        graph = transformed;
+        cfg.package_name = root;
+        child_order::apply(&mut graph, cfg.child_order);
+    }
+
+    if cfg.dedupe == Dedupe::Off && cfg.max_nodes.is_none() && !cli.json && !cli.bfs {
+        println!("\nNOTE: full expansion is limited to {} nodes (set MaxNodes to change).", FULL_EXPANSION_BUDGET);
+    }
+
+    if cli.json {
+        // Дерево в JSON вместо текста
+        let direction = if reverse { Direction::Reverse } else { Direction::Forward };
+        let json = output::render_tree(&graph, &cfg, output::OutputFormat::Json, direction, TextStyle::default());
+        print!("{}", json);
+    } else if cli.bfs {
+        let direction = if reverse { Direction::Reverse } else { Direction::Forward };
+        println!("\nDependencies for '{}' by level", cfg.package_name);
+        let (levels, truncation) = graph.bfs_levels(&cfg.package_name, &cfg.exclude_filter, limits, direction);
+        print!("{}", render::render_levels(&levels));
+        truncation.print_summary("Tree");
+    } else if reverse {
+        if !full_graph {
+            println!("\nNOTE: reverse mode is only meaningful with a full graph.");
+            println!("Showing who depends on '{}' among the known nodes only:\n", cfg.package_name);
+        } else {
+            println!("\nReverse dependencies for '{}' ", cfg.package_name);
+        }
+        graph.print_reverse_tree(&cfg.package_name, &cfg.exclude_filter, limits, TextStyle::terminal(&cfg))
+            .print_summary("Tree");
+    } else if full_graph || cfg.ascii_tree {
+        println!("\nDependencies for '{}' ", cfg.package_name);
+        graph.print_tree(&cfg.package_name, &cfg.exclude_filter, limits, TextStyle::terminal(&cfg))
+            .print_summary("Tree");
+    } else {
+        println!("\nDirect package dependencies '{}':", cfg.package_name);
+        for dep in graph.dependencies(&cfg.package_name) {
+            println!("- {}", dep);
+        }
+    }
+
+    // Формирование выходных файлов из секции <Output> с учётом флагов командной строки
+    let mut outputs = cfg.outputs.clone();
+    output::apply_overrides(&mut outputs, &cli.outputs);
+    output::write_outputs(&graph, &cfg, &outputs);
+}
+
+/// Код возврата `diff --exit-code`, если сравнение не удалось выполнить (1 - найдены изменения)
+const EXIT_DIFF_FAILED: i32 = 2;
+
+/// Завершение после ошибки загрузки: с --exit-code код возврата отличается от "нет изменений"
+fn failed(exit_code: bool) {
+    if exit_code {
+        std::process::exit(EXIT_DIFF_FAILED);
     }
 }
 
@@ -205,6 +391,66 @@ fn print_config_error(err: ConfigError) {
     }
 }
 
+/// Замер производительности на графах заданного размера
+#[cfg(feature = "bench")]
+fn run_bench(size: Option<&str>) {
+    match size.map(|v| config::parse_limit("nodes", v, 2)) {
+        Some(Err(e)) => print_config_error(e),
+        Some(Ok(n)) => bench::run(n),
+        None => bench::run(bench::DEFAULT_NODES),
+    }
+}
+
+/// Без feature `bench` счётчик памяти не встраивается в программу, а команда недоступна
+#[cfg(not(feature = "bench"))]
+fn run_bench(_size: Option<&str>) {
+    eprintln!("ERROR: bench is not built in, rebuild with `cargo run --release --features bench -- ...`");
+}
+
+/// Запись сгенерированного тестового репозитория в файл или на стандартный вывод
+fn run_generate(params: &generate::Params, path: Option<&str>) {
+    let deps = generate::generate(params);
+    let text = generate::to_test_repo(params, &deps);
+    match path {
+        Some(path) => match fs::write(path, &text) {
+            Ok(()) => println!(
+                "Generated {} packages and {} dependencies into {}",
+                deps.len(),
+                deps.iter().map(Vec::len).sum::<usize>(),
+                path
+            ),
+            Err(e) => eprintln!("OUTPUT ERROR: cannot write {}: {}", path, e),
+        },
+        None => print!("{}", text),
+    }
+}
+
+/// Обработчик ошибок источника зависимостей
+fn print_source_error(err: SourceError) {
+    match err {
+        SourceError::Cargo(e) => print_cargo_error(e),
+        SourceError::TestRepo(e) => print_test_repo_error(e),
+        SourceError::Lockfile(e) => print_lockfile_error(e),
+    }
+}
+
+/// Обработчик ошибок при работе со снимком графа
+fn print_snapshot_error(err: SnapshotError) {
+    eprintln!("SNAPSHOT ERROR: {}", err);
+}
+
+/// Обработчик ошибок при работе с Cargo.lock
+fn print_lockfile_error(err: LockfileError) {
+    match err {
+        LockfileError::Read(msg) => {
+            eprintln!("LOCKFILE ERROR: {}", msg);
+        }
+        LockfileError::Parse { line, msg } => {
+            eprintln!("LOCKFILE ERROR: invalid format at line {}: {}", line, msg);
+        }
+    }
+}
+
 /// Обработчик ошибок при работе с локальным или удалённым Cargo.toml
 fn print_cargo_error(err: CargoParseError) {
     match err {
@@ -222,12 +468,5 @@ fn print_cargo_error(err: CargoParseError) {
 
 /// Обработчик ошибок при работе с тестовым репозиторием
 fn print_test_repo_error(err: test_repo::TestRepoError) {
-    match err {
-        test_repo::TestRepoError::ReadError(msg) => {
-            eprintln!("TEST REPO ERROR: {}", msg);
-        }
-        test_repo::TestRepoError::ParseError(msg) => {
-            eprintln!("TEST REPO ERROR: invalid file format: {}", msg);
-        }
-    }
+    eprintln!("TEST REPO ERROR: {}", err);
 }
\ No newline at end of file
@@ -0,0 +1,192 @@
+// Модуль для построения порядка сборки: топологическая сортировка и уровни параллельной сборки
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::graph::DependencyGraph;
+use crate::json::JsonValue;
+use crate::scc;
+
+/// Порядок сборки пакетов
+#[derive(Debug)]
+pub struct BuildOrder {
+    /// Топологический порядок (зависимости раньше зависящих); пуст при наличии циклов
+    pub order: Vec<String>,
+    /// Уровни параллельной сборки: уровень 0 - листья, уровень N - длина наибольшего пути до листа
+    pub levels: Vec<Vec<String>>,
+    /// Компоненты сильной связности, из-за которых порядок не существует
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl BuildOrder {
+    /// Существует ли топологический порядок
+    pub fn is_acyclic(&self) -> bool {
+        self.cycles.is_empty()
+    }
+
+    /// Текстовое представление
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        if self.is_acyclic() {
+            out.push_str("Build order:\n");
+            for (i, name) in self.order.iter().enumerate() {
+                out.push_str(&format!("  {:>3}. {}\n", i + 1, name));
+            }
+        } else {
+            out.push_str("No topological order: the graph has cycles in\n");
+            for comp in &self.cycles {
+                out.push_str(&format!("  {{{}}}\n", comp.join(", ")));
+            }
+        }
+
+        out.push_str("\nParallel build levels:\n");
+        for (i, level) in self.levels.iter().enumerate() {
+            out.push_str(&format!("  level {}: {}\n", i, level.join(" ")));
+        }
+        out
+    }
+
+    /// Представление в JSON
+    pub fn to_json(&self, root: &str) -> JsonValue {
+        JsonValue::object(vec![
+            ("root", root.into()),
+            ("acyclic", self.is_acyclic().into()),
+            ("order", JsonValue::strings(&self.order)),
+            ("levels", JsonValue::Array(self.levels.iter().map(JsonValue::strings).collect())),
+            ("cycles", JsonValue::Array(self.cycles.iter().map(JsonValue::strings).collect())),
+        ])
+    }
+}
+
+/// Построение порядка сборки. При наличии циклов уровни строятся по конденсации графа,
+/// где каждая компонента сильной связности представлена одним узлом.
+pub fn build_order(graph: &DependencyGraph) -> BuildOrder {
+    let cycles: Vec<Vec<String>> = scc::strongly_connected_components(graph)
+        .into_iter()
+        .filter(|c| c.len() > 1 || graph.dependencies(&c[0]).any(|d| d == c[0]))
+        .collect();
+
+    if cycles.is_empty() {
+        let order = topological_order(graph);
+        let levels = levels(graph, &order);
+        return BuildOrder { order, levels, cycles };
+    }
+
+    let condensed = scc::condense(graph).graph;
+    let order = topological_order(&condensed);
+    let levels = levels(&condensed, &order);
+    BuildOrder { order: Vec::new(), levels, cycles }
+}
+
+/// Топологическая сортировка (алгоритм Кана) для ациклического графа:
+/// пакет попадает в порядок после всех своих зависимостей, при равенстве - по алфавиту
+pub fn topological_order(graph: &DependencyGraph) -> Vec<String> {
+    let mut remaining: HashMap<&str, usize> = graph
+        .node_names()
+        .into_iter()
+        .map(|n| (n, graph.dependencies(n).count()))
+        .collect();
+    let mut ready: BTreeSet<&str> = remaining.iter().filter(|(_, c)| **c == 0).map(|(n, _)| *n).collect();
+    let mut order = Vec::with_capacity(remaining.len());
+
+    while let Some(name) = ready.pop_first() {
+        order.push(name.to_string());
+        for parent in graph.dependents(name) {
+            if let Some(count) = remaining.get_mut(parent) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(parent);
+                }
+            }
+        }
+    }
+
+    order
+}
+
+// Уровни по длине наибольшего пути до листа; `order` - топологический порядок
+fn levels(graph: &DependencyGraph, order: &[String]) -> Vec<Vec<String>> {
+    let mut level: HashMap<&str, usize> = HashMap::new();
+    let mut levels: Vec<Vec<String>> = Vec::new();
+    for name in order {
+        let l = graph
+            .dependencies(name)
+            .filter_map(|d| level.get(d))
+            .map(|l| l + 1)
+            .max()
+            .unwrap_or(0);
+        level.insert(name, l);
+        if levels.len() <= l {
+            levels.resize(l + 1, Vec::new());
+        }
+        levels[l].push(name.clone());
+    }
+    for l in &mut levels {
+        l.sort();
+    }
+    levels
+}
+
+/// Уровень сборки каждого узла исходного графа (узлы одного цикла получают общий уровень)
+pub fn level_map(graph: &DependencyGraph) -> HashMap<String, usize> {
+    let condensation = scc::condense(graph);
+    let order = topological_order(&condensation.graph);
+    let mut by_component = HashMap::new();
+    for (i, level) in levels(&condensation.graph, &order).into_iter().enumerate() {
+        for name in level {
+            by_component.insert(name, i);
+        }
+    }
+    condensation
+        .component_of
+        .into_iter()
+        .map(|(node, comp)| (node, by_component[&comp]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in edges {
+            g.add_edge(from, to);
+        }
+        g
+    }
+
+    #[test]
+    fn orders_dag_with_alphabetical_ties() {
+        let mut g = graph(&[("A", "C"), ("A", "B"), ("B", "D"), ("C", "D")]);
+        g.ensure_node("E");
+        let order = build_order(&g);
+        assert!(order.is_acyclic());
+        assert_eq!(order.order, ["D", "B", "C", "A", "E"]);
+        assert_eq!(order.levels, [vec!["D", "E"], vec!["B", "C"], vec!["A"]]);
+    }
+
+    #[test]
+    fn independent_packages_are_ordered_by_name() {
+        let mut g = DependencyGraph::new();
+        for name in ["zlib", "Mio", "libc", "anyhow"] {
+            g.ensure_node(name);
+        }
+        assert_eq!(topological_order(&g), ["Mio", "anyhow", "libc", "zlib"]);
+        assert_eq!(build_order(&g).levels.len(), 1);
+    }
+
+    #[test]
+    fn cycles_share_a_level() {
+        let g = graph(&[("A", "B"), ("B", "C"), ("C", "B"), ("C", "D"), ("D", "D")]);
+        let order = build_order(&g);
+        assert!(!order.is_acyclic());
+        assert!(order.order.is_empty());
+        assert_eq!(order.cycles, [vec!["D"], vec!["B", "C"]]);
+        assert_eq!(order.levels, [vec!["D"], vec!["{B, C}"], vec!["A"]]);
+
+        let levels = level_map(&g);
+        let mut levels: Vec<(&str, usize)> = levels.iter().map(|(n, l)| (n.as_str(), *l)).collect();
+        levels.sort();
+        assert_eq!(levels, [("A", 2), ("B", 1), ("C", 1), ("D", 0)]);
+    }
+}
@@ -0,0 +1,481 @@
+// Модуль для описания и формирования выходных файлов (дерево, JSON, HTML, D2, изображение)
+
+use std::collections::HashSet;
+use std::fs;
+use std::str::FromStr;
+
+use which::which;
+
+use crate::config::AppConfig;
+use crate::d2;
+use crate::graph::{DependencyGraph, Direction, EdgeInfo, FirstSeen, TreeVisitor};
+use crate::order;
+use crate::render::{HtmlRenderer, JsonRenderer, TextStyle};
+
+/// Формат выходного файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Текстовое дерево зависимостей
+    Tree,
+    /// Дерево зависимостей в JSON
+    Json,
+    /// Дерево зависимостей в виде HTML-страницы
+    Html,
+    /// Диаграмма на языке D2
+    D2,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "tree" => Ok(OutputFormat::Tree),
+            "json" => Ok(OutputFormat::Json),
+            "html" => Ok(OutputFormat::Html),
+            "d2" => Ok(OutputFormat::D2),
+            other => Err(format!("unknown format '{other}', expected 'tree', 'json', 'html' or 'd2'")),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "forward" => Ok(Direction::Forward),
+            "reverse" => Ok(Direction::Reverse),
+            other => Err(format!("unknown direction '{other}', expected 'forward' or 'reverse'")),
+        }
+    }
+}
+
+/// Описание одного выхода из секции <Output>
+#[derive(Debug, Clone)]
+pub struct OutputSpec {
+    pub format: OutputFormat,
+    /// Путь к файлу; для дерева без пути вывод идёт на экран
+    pub path: Option<String>,
+    pub direction: Direction,
+    /// Путь к изображению, которое рендерит d2 CLI
+    pub render: Option<String>,
+    /// Движок раскладки d2 (dagre, elk, ...)
+    pub layout: Option<String>,
+    /// Открыть изображение после рендера
+    pub open: bool,
+    /// Группировать узлы D2 по уровням параллельной сборки
+    pub levels: bool,
+    /// Экспорт в D2 всего графа, а не только пакетов из дерева от корня
+    pub whole_graph: bool,
+}
+
+impl OutputSpec {
+    /// Выход по умолчанию для заданного формата
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            path: None,
+            direction: Direction::Forward,
+            render: None,
+            layout: None,
+            open: false,
+            levels: false,
+            whole_graph: false,
+        }
+    }
+
+    /// Разбор атрибутов элемента <Target .../>
+    pub fn from_attributes(attrs: &[(String, String)]) -> Result<Self, String> {
+        let format = attrs
+            .iter()
+            .find(|(k, _)| k == "format")
+            .ok_or("missing attribute 'format'")?
+            .1
+            .parse()?;
+
+        let mut spec = OutputSpec::new(format);
+        for (key, value) in attrs {
+            match key.as_str() {
+                "format" => {}
+                "path" => spec.path = Some(value.clone()),
+                "direction" => spec.direction = value.parse()?,
+                "render" => spec.render = Some(value.clone()),
+                "layout" => spec.layout = Some(value.clone()),
+                "open" => spec.open = parse_bool(value)?,
+                "levels" => spec.levels = parse_bool(value)?,
+                "whole-graph" => spec.whole_graph = parse_bool(value)?,
+                other => return Err(format!("unknown attribute '{other}'")),
+            }
+        }
+
+        if spec.format != OutputFormat::D2 && (spec.render.is_some() || spec.layout.is_some() || spec.levels || spec.whole_graph)
+        {
+            return Err("render options are only supported for format 'd2'".into());
+        }
+        Ok(spec)
+    }
+}
+
+/// Переопределения выходов флагами командной строки
+#[derive(Debug, Default)]
+pub struct OutputOverrides {
+    pub d2_path: Option<String>,
+    pub render_path: Option<String>,
+    pub open: bool,
+    pub reverse: bool,
+    pub levels: bool,
+    pub whole_graph: bool,
+}
+
+/// Применение флагов командной строки к выходам из конфигурации
+pub fn apply_overrides(outputs: &mut Vec<OutputSpec>, cli: &OutputOverrides) {
+    if cli.d2_path.is_some() || cli.render_path.is_some() {
+        let idx = match outputs.iter().position(|o| o.format == OutputFormat::D2) {
+            Some(i) => i,
+            None => {
+                outputs.push(OutputSpec::new(OutputFormat::D2));
+                outputs.len() - 1
+            }
+        };
+        let d2_out = &mut outputs[idx];
+        if let Some(p) = &cli.d2_path {
+            d2_out.path = Some(p.clone());
+        }
+        if let Some(r) = &cli.render_path {
+            d2_out.render = Some(r.clone());
+        }
+    }
+
+    for o in outputs.iter_mut() {
+        if cli.reverse {
+            o.direction = Direction::Reverse;
+        }
+        if cli.open && o.render.is_some() {
+            o.open = true;
+        }
+        if cli.levels && o.format == OutputFormat::D2 {
+            o.levels = true;
+        }
+        if cli.whole_graph && o.format == OutputFormat::D2 {
+            o.whole_graph = true;
+        }
+    }
+}
+
+/// Формирование всех выходов; ошибки одного выхода не прерывают остальные
+pub fn write_outputs(graph: &DependencyGraph, cfg: &AppConfig, outputs: &[OutputSpec]) {
+    for spec in outputs {
+        match spec.format {
+            OutputFormat::Tree | OutputFormat::Json | OutputFormat::Html => write_tree(graph, cfg, spec),
+            OutputFormat::D2 => write_d2(graph, cfg, spec),
+        }
+    }
+}
+
+// Запись дерева (текст, JSON или HTML) в файл или на экран
+fn write_tree(graph: &DependencyGraph, cfg: &AppConfig, spec: &OutputSpec) {
+    let style = match spec.path {
+        Some(_) => TextStyle::file(cfg),
+        None => TextStyle::terminal(cfg),
+    };
+    let text = render_tree(graph, cfg, spec.format, spec.direction, style);
+    match &spec.path {
+        Some(path) => match fs::write(path, &text) {
+            Ok(()) => println!("Tree saved to {}", path),
+            Err(e) => eprintln!("OUTPUT ERROR: cannot write {}: {}", path, e),
+        },
+        None => print!("\n{}", text),
+    }
+}
+
+/// Формирование дерева зависимостей в заданном формате (для D2 - текстовое дерево);
+/// `style` действует только на текстовое дерево
+pub fn render_tree(
+    graph: &DependencyGraph,
+    cfg: &AppConfig,
+    format: OutputFormat,
+    direction: Direction,
+    style: TextStyle,
+) -> String {
+    let (root, filter, limits) = (&cfg.package_name, &cfg.exclude_filter, cfg.limits());
+    match format {
+        OutputFormat::Json => {
+            let mut json = JsonRenderer::new(graph);
+            graph.walk(root, filter, limits, direction, &mut json);
+            json.finish().to_pretty_string() + "\n"
+        }
+        OutputFormat::Html => {
+            let mut html = HtmlRenderer::default();
+            graph.walk(root, filter, limits, direction, &mut html);
+            let title = match direction {
+                Direction::Forward => format!("Dependencies for '{}'", root),
+                Direction::Reverse => format!("Reverse dependencies for '{}'", root),
+            };
+            html.finish(&title)
+        }
+        OutputFormat::Tree | OutputFormat::D2 => graph.render_tree(root, filter, limits, direction, style).0,
+    }
+}
+
+// Экспорт в D2 и рендер изображения. Без whole-graph в диаграмму попадают только пакеты и рёбра
+// дерева от корня (с теми же фильтром, лимитами и направлением), поэтому она совпадает с выводом
+fn write_d2(graph: &DependencyGraph, cfg: &AppConfig, spec: &OutputSpec) {
+    let d2_text = render_d2_text(graph, cfg, spec);
+    let path = spec.path.as_deref().unwrap_or("graph.d2");
+    if let Err(e) = fs::write(path, &d2_text) {
+        eprintln!("D2 ERROR: cannot write {}: {}", path, e);
+        return;
+    }
+    println!("D2 saved to {}", path);
+
+    if let Some(out_img) = &spec.render {
+        render_d2(path, out_img, spec);
+    }
+}
+
+// Текст D2 для выхода `spec`
+fn render_d2_text(graph: &DependencyGraph, cfg: &AppConfig, spec: &OutputSpec) -> String {
+    let scoped;
+    let graph = match spec.whole_graph {
+        true => graph,
+        false => {
+            scoped = tree_subgraph(graph, cfg, spec.direction);
+            &scoped
+        }
+    };
+    let levels = spec.levels.then(|| order::level_map(graph));
+    let reverse = spec.direction == Direction::Reverse;
+    d2::to_d2(graph, &cfg.package_name, reverse, levels.as_ref(), cfg.d2_theme)
+}
+
+/// Подграф из пакетов и рёбер, пройденных при выводе дерева от корня
+pub fn tree_subgraph(graph: &DependencyGraph, cfg: &AppConfig, direction: Direction) -> DependencyGraph {
+    let mut collector =
+        TreeCollector { direction, nodes: HashSet::new(), edges: HashSet::new(), path: Vec::new() };
+    graph.walk(&cfg.package_name, &cfg.exclude_filter, cfg.limits(), direction, &mut collector);
+
+    let mut out = graph.subgraph(|name| collector.nodes.contains(name));
+    // Рёбра между попавшими пакетами, не показанные в дереве (например, у узлов на границе глубины)
+    let extra: Vec<(String, String)> = out
+        .node_names()
+        .into_iter()
+        .flat_map(|name| out.dependencies(name).map(move |dep| (name.to_string(), dep.to_string())))
+        .filter(|edge| !collector.edges.contains(edge))
+        .collect();
+    for (name, dep) in &extra {
+        out.remove_edge(name, dep);
+    }
+    out
+}
+
+// Сбор пакетов и рёбер дерева; рёбра хранятся в направлении графа (пакет -> зависимость)
+struct TreeCollector {
+    direction: Direction,
+    nodes: HashSet<String>,
+    edges: HashSet<(String, String)>,
+    /// Текущий путь от корня
+    path: Vec<String>,
+}
+
+impl TreeCollector {
+    fn edge_to(&mut self, name: &str, depth: usize) {
+        let Some(parent) = depth.checked_sub(1).and_then(|d| self.path.get(d)) else { return };
+        let edge = match self.direction {
+            Direction::Forward => (parent.clone(), name.to_string()),
+            Direction::Reverse => (name.to_string(), parent.clone()),
+        };
+        self.edges.insert(edge);
+    }
+}
+
+impl TreeVisitor for TreeCollector {
+    fn enter(&mut self, name: &str, depth: usize, _edge: Option<&EdgeInfo>) {
+        self.edge_to(name, depth);
+        self.nodes.insert(name.to_string());
+        self.path.truncate(depth);
+        self.path.push(name.to_string());
+    }
+
+    fn leave(&mut self, _name: &str, depth: usize) {
+        self.path.truncate(depth);
+    }
+
+    fn cycle(&mut self, name: &str, depth: usize, _edge: Option<&EdgeInfo>) {
+        self.edge_to(name, depth);
+    }
+
+    fn visited(&mut self, name: &str, depth: usize, _edge: Option<&EdgeInfo>, _first: Option<FirstSeen<'_>>) {
+        self.edge_to(name, depth);
+    }
+}
+
+// Рендер изображения внешней утилитой d2
+fn render_d2(input: &str, out_img: &str, spec: &OutputSpec) {
+    let bin = match which("d2") {
+        Ok(bin) => bin,
+        Err(_) => {
+            eprintln!("D2 RENDER SKIPPED: 'd2' CLI not found in PATH.");
+            return;
+        }
+    };
+
+    println!("Rendering with {:?}: {} -> {}", bin, input, out_img);
+    let mut cmd = std::process::Command::new(bin);
+    if let Some(layout) = &spec.layout {
+        cmd.arg(format!("--layout={}", layout));
+    }
+    let status = cmd.arg(input).arg(out_img).status();
+
+    match status {
+        Ok(s) if s.success() => {
+            println!("Rendered image: {}", out_img);
+            if spec.open
+                && let Err(e) = open::that(out_img)
+            {
+                eprintln!("OPEN WARN: cannot open {}: {}", out_img, e);
+            }
+        }
+        Ok(s) => {
+            eprintln!("D2 RENDER ERROR: exit code {:?}", s.code());
+        }
+        Err(e) => {
+            eprintln!("D2 RENDER ERROR: {}", e);
+        }
+    }
+}
+
+// Разбор булева атрибута
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim() {
+        "true" | "True" | "TRUE" => Ok(true),
+        "false" | "False" | "FALSE" => Ok(false),
+        other => Err(format!("expected true/false, got '{other}'")),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::child_order::{self, ChildOrder};
+    use crate::config;
+    use crate::graph::Dedupe;
+    use crate::render::TreeStyle;
+    use crate::test_repo;
+
+    // Эталонные файлы лежат в testdata/golden/<репозиторий>/<порядок>/;
+    // UPDATE_GOLDEN=1 cargo test перезаписывает их текущим выводом
+    const REPOS: [(&str, &str, &str); 2] =
+        [("test_repo", "test_repo.txt", "A"), ("features", "testdata/golden/features.txt", "app")];
+    const ORDERS: [(&str, ChildOrder); 4] = [
+        ("declaration", ChildOrder::Declaration),
+        ("alpha", ChildOrder::Alpha),
+        ("size", ChildOrder::Size),
+        ("depth", ChildOrder::Depth),
+    ];
+    const STYLES: [(&str, TreeStyle); 3] =
+        [("indent", TreeStyle::Indent), ("unicode", TreeStyle::Unicode), ("ascii", TreeStyle::Ascii)];
+
+    fn manifest_path(relative: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(relative)
+    }
+
+    fn load(file: &str, root: &str, order: ChildOrder) -> (DependencyGraph, AppConfig) {
+        let path = manifest_path(file);
+        let repo = test_repo::load_test_repo(path.to_str().unwrap(), false).unwrap();
+        let mut graph = repo.to_graph();
+        child_order::apply(&mut graph, order);
+        let xml = format!(
+            "<Config><PackageName>{root}</PackageName><RepoSource>{file}</RepoSource>\
+             <Mode>test</Mode><AsciiTree>true</AsciiTree></Config>"
+        );
+        (graph, config::parse_xml(&xml).unwrap())
+    }
+
+    // Сравнение с эталоном; все расхождения собираются, чтобы одна ошибка не скрывала остальные
+    fn check(golden: &str, actual: &str, failures: &mut Vec<String>) {
+        let path = manifest_path("testdata/golden").join(golden);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!("{golden} differs:\n--- expected\n{expected}--- actual\n{actual}")),
+            Err(e) => failures.push(format!("{golden}: {e}")),
+        }
+    }
+
+    fn assert_no_failures(failures: Vec<String>) {
+        assert!(
+            failures.is_empty(),
+            "{}\n\nrun `UPDATE_GOLDEN=1 cargo test` if the change is intended",
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn tree_golden() {
+        let mut failures = Vec::new();
+        for (repo, file, root) in REPOS {
+            for (order_name, order) in ORDERS {
+                let (graph, mut cfg) = load(file, root, order);
+                for (style_name, tree) in STYLES {
+                    let style = TextStyle { tree, ..TextStyle::file(&cfg) };
+                    for (suffix, direction) in [("", Direction::Forward), ("-reverse", Direction::Reverse)] {
+                        let text = render_tree(&graph, &cfg, OutputFormat::Tree, direction, style);
+                        check(&format!("{repo}/{order_name}/tree-{style_name}{suffix}.txt"), &text, &mut failures);
+                    }
+                }
+
+                // Ссылки на первое вхождение включаются только явно
+                cfg.dedupe = Dedupe::Reference;
+                let text = render_tree(&graph, &cfg, OutputFormat::Tree, Direction::Forward, TextStyle::file(&cfg));
+                check(&format!("{repo}/{order_name}/tree-reference.txt"), &text, &mut failures);
+            }
+        }
+        assert_no_failures(failures);
+    }
+
+    #[test]
+    fn json_golden() {
+        let mut failures = Vec::new();
+        for (repo, file, root) in REPOS {
+            for (order_name, order) in ORDERS {
+                let (graph, mut cfg) = load(file, root, order);
+                let text = render_tree(&graph, &cfg, OutputFormat::Json, Direction::Forward, TextStyle::file(&cfg));
+                check(&format!("{repo}/{order_name}/tree.json"), &text, &mut failures);
+
+                cfg.dedupe = Dedupe::Reference;
+                let text = render_tree(&graph, &cfg, OutputFormat::Json, Direction::Forward, TextStyle::file(&cfg));
+                check(&format!("{repo}/{order_name}/tree-reference.json"), &text, &mut failures);
+            }
+        }
+        assert_no_failures(failures);
+    }
+
+    #[test]
+    fn d2_golden() {
+        let mut failures = Vec::new();
+        for (repo, file, root) in REPOS {
+            for (order_name, order) in ORDERS {
+                let (graph, mut cfg) = load(file, root, order);
+                for (suffix, direction) in [("", Direction::Forward), ("-reverse", Direction::Reverse)] {
+                    let spec = OutputSpec { direction, ..OutputSpec::new(OutputFormat::D2) };
+                    let text = render_d2_text(&graph, &cfg, &spec);
+                    check(&format!("{repo}/{order_name}/graph{suffix}.d2"), &text, &mut failures);
+                }
+
+                cfg.d2_theme = d2::D2Theme::Plain;
+                let levels = OutputSpec { levels: true, ..OutputSpec::new(OutputFormat::D2) };
+                let text = render_d2_text(&graph, &cfg, &levels);
+                check(&format!("{repo}/{order_name}/graph-plain-levels.d2"), &text, &mut failures);
+            }
+        }
+        assert_no_failures(failures);
+    }
+}
@@ -0,0 +1,498 @@
+// Модуль с языком запросов к графу зависимостей (в духе bazel query)
+//
+// Грамматика:
+//   expr := term (op term)*            op: + union, ^ intersect, - except (левоассоциативно)
+//   term := deps(expr [, depth]) | rdeps(expr [, depth]) | allpaths(expr, expr)
+//         | attr(name, "pattern", expr) | (expr) | имя | "имя"
+// В именах и шаблонах допустимы подстановочные символы * и ?
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use thiserror::Error;
+
+use crate::graph::DependencyGraph;
+
+/// Перечисление возможных ошибок при разборе и вычислении запроса
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("syntax error at position {pos}: {msg}")]
+    Syntax { pos: usize, msg: String },
+
+    #[error("no package matches '{0}'")]
+    UnknownPackage(String),
+}
+
+/// Выражение запроса
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Пакеты, имя которых совпадает с шаблоном
+    Packages(String),
+    /// Транзитивные зависимости (включая сами пакеты), не глубже `depth`
+    Deps(Box<Expr>, Option<usize>),
+    /// Транзитивные обратные зависимости (включая сами пакеты), не глубже `depth`
+    Rdeps(Box<Expr>, Option<usize>),
+    /// Узлы, лежащие на каком-либо пути из первого множества во второе
+    AllPaths(Box<Expr>, Box<Expr>),
+    /// Пакеты множества, у которых атрибут совпадает с шаблоном
+    Attr { name: String, pattern: String, expr: Box<Expr> },
+    Union(Box<Expr>, Box<Expr>),
+    Intersect(Box<Expr>, Box<Expr>),
+    Except(Box<Expr>, Box<Expr>),
+}
+
+/// Разбор и вычисление запроса
+pub fn run(graph: &DependencyGraph, text: &str) -> Result<BTreeSet<String>, QueryError> {
+    evaluate(graph, &parse(text)?)
+}
+
+/// Разбор текста запроса
+pub fn parse(text: &str) -> Result<Expr, QueryError> {
+    let mut p = Parser { tokens: tokenize(text)?, pos: 0, end: text.len() };
+    let expr = p.expr()?;
+    if let Some(t) = p.tokens.get(p.pos) {
+        return Err(syntax(t.pos, format!("unexpected {}", t.kind.describe())));
+    }
+    Ok(expr)
+}
+
+/// Вычисление выражения на графе
+pub fn evaluate(graph: &DependencyGraph, expr: &Expr) -> Result<BTreeSet<String>, QueryError> {
+    Ok(match expr {
+        Expr::Packages(pattern) => {
+            let found: BTreeSet<String> = graph
+                .node_names()
+                .into_iter()
+                .filter(|n| glob_match(pattern, n))
+                .map(str::to_string)
+                .collect();
+            if found.is_empty() {
+                return Err(QueryError::UnknownPackage(pattern.clone()));
+            }
+            found
+        }
+        Expr::Deps(e, depth) => {
+            let start = evaluate(graph, e)?;
+            closure(&start, *depth, |n| graph.dependencies(n).map(str::to_string).collect())
+        }
+        Expr::Rdeps(e, depth) => {
+            let start = evaluate(graph, e)?;
+            closure(&start, *depth, |n| graph.dependents(n).map(str::to_string).collect())
+        }
+        Expr::AllPaths(from, to) => {
+            let (from, to) = (evaluate(graph, from)?, evaluate(graph, to)?);
+            let down = closure(&from, None, |n| graph.dependencies(n).map(str::to_string).collect());
+            let up = closure(&to, None, |n| graph.dependents(n).map(str::to_string).collect());
+            down.intersection(&up).cloned().collect()
+        }
+        Expr::Attr { name, pattern, expr } => evaluate(graph, expr)?
+            .into_iter()
+            .filter(|n| attribute(graph, n, name).is_some_and(|v| glob_match(pattern, &v)))
+            .collect(),
+        Expr::Union(a, b) => evaluate(graph, a)?.union(&evaluate(graph, b)?).cloned().collect(),
+        Expr::Intersect(a, b) => evaluate(graph, a)?.intersection(&evaluate(graph, b)?).cloned().collect(),
+        Expr::Except(a, b) => evaluate(graph, a)?.difference(&evaluate(graph, b)?).cloned().collect(),
+    })
+}
+
+// Значение атрибута узла; name и version доступны как встроенные атрибуты
+fn attribute(graph: &DependencyGraph, node: &str, name: &str) -> Option<String> {
+    let attributes = graph.attributes(node)?;
+    match name {
+        "name" => Some(node.to_string()),
+        "version" => graph.version(node).map(str::to_string),
+        _ => attributes.get(name).cloned(),
+    }
+}
+
+// Обход в ширину от множества узлов с ограничением глубины
+fn closure(
+    start: &BTreeSet<String>,
+    depth: Option<usize>,
+    next: impl Fn(&str) -> Vec<String>,
+) -> BTreeSet<String> {
+    let mut dist: HashMap<String, usize> = start.iter().map(|n| (n.clone(), 0)).collect();
+    let mut queue: VecDeque<String> = start.iter().cloned().collect();
+    while let Some(v) = queue.pop_front() {
+        let d = dist[&v];
+        if depth.is_some_and(|m| d >= m) {
+            continue;
+        }
+        for w in next(&v) {
+            if !dist.contains_key(&w) {
+                dist.insert(w.clone(), d + 1);
+                queue.push_back(w);
+            }
+        }
+    }
+    dist.into_keys().collect()
+}
+
+/// Сопоставление с шаблоном: * - любая подстрока, ? - любой символ
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    let (mut pi, mut ti) = (0, 0);
+    // Позиция последней звёздочки и символа текста, с которого она начала сопоставление
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Str(String),
+    LParen,
+    RParen,
+    Comma,
+    Plus,
+    Caret,
+    Minus,
+}
+
+impl TokenKind {
+    fn describe(&self) -> String {
+        match self {
+            TokenKind::Word(w) => format!("'{}'", w),
+            TokenKind::Str(s) => format!("\"{}\"", s),
+            TokenKind::LParen => "'('".into(),
+            TokenKind::RParen => "')'".into(),
+            TokenKind::Comma => "','".into(),
+            TokenKind::Plus => "'+'".into(),
+            TokenKind::Caret => "'^'".into(),
+            TokenKind::Minus => "'-'".into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    /// Смещение начала лексемы в тексте запроса
+    pos: usize,
+}
+
+fn syntax(pos: usize, msg: impl Into<String>) -> QueryError {
+    QueryError::Syntax { pos, msg: msg.into() }
+}
+
+// Символы имени пакета; '-' внутри имени не считается оператором
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '@' | '*' | '?' | '/' | ':')
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(pos, c)) = chars.peek() {
+        let simple = match c {
+            '(' => Some(TokenKind::LParen),
+            ')' => Some(TokenKind::RParen),
+            ',' => Some(TokenKind::Comma),
+            '+' => Some(TokenKind::Plus),
+            '^' => Some(TokenKind::Caret),
+            '-' => Some(TokenKind::Minus),
+            _ => None,
+        };
+        if c.is_whitespace() {
+            chars.next();
+        } else if let Some(kind) = simple {
+            chars.next();
+            tokens.push(Token { kind, pos });
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, e)) => s.push(e),
+                        None => return Err(syntax(pos, "unterminated string")),
+                    },
+                    Some((_, ch)) => s.push(ch),
+                    None => return Err(syntax(pos, "unterminated string")),
+                }
+            }
+            tokens.push(Token { kind: TokenKind::Str(s), pos });
+        } else if is_word_char(c) {
+            let mut w = String::new();
+            while let Some(&(_, ch)) = chars.peek() {
+                if !is_word_char(ch) {
+                    break;
+                }
+                w.push(ch);
+                chars.next();
+            }
+            tokens.push(Token { kind: TokenKind::Word(w), pos });
+        } else {
+            return Err(syntax(pos, format!("unexpected character '{}'", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+// Рекурсивный спуск по списку лексем
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Длина текста (позиция для ошибок в конце запроса)
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn here(&self) -> usize {
+        self.tokens.get(self.pos).map(|t| t.pos).unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Result<TokenKind, QueryError> {
+        let t = self.tokens.get(self.pos).ok_or_else(|| syntax(self.end, "unexpected end of query"))?;
+        self.pos += 1;
+        Ok(t.kind.clone())
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<(), QueryError> {
+        let pos = self.here();
+        let got = self.next()?;
+        if got == kind {
+            Ok(())
+        } else {
+            Err(syntax(pos, format!("expected {}, got {}", kind.describe(), got.describe())))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(TokenKind::Plus) => Expr::Union,
+                Some(TokenKind::Caret) => Expr::Intersect,
+                Some(TokenKind::Minus) => Expr::Except,
+                Some(TokenKind::Word(w)) if w == "union" => Expr::Union,
+                Some(TokenKind::Word(w)) if w == "intersect" => Expr::Intersect,
+                Some(TokenKind::Word(w)) if w == "except" => Expr::Except,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = op(Box::new(left), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, QueryError> {
+        let pos = self.here();
+        match self.next()? {
+            TokenKind::LParen => {
+                let e = self.expr()?;
+                self.expect(TokenKind::RParen)?;
+                Ok(e)
+            }
+            TokenKind::Str(s) => Ok(Expr::Packages(s)),
+            TokenKind::Word(w) if self.peek() == Some(&TokenKind::LParen) => {
+                self.pos += 1;
+                let e = self.function(&w, pos)?;
+                self.expect(TokenKind::RParen)?;
+                Ok(e)
+            }
+            TokenKind::Word(w) => Ok(Expr::Packages(w)),
+            other => Err(syntax(pos, format!("unexpected {}", other.describe()))),
+        }
+    }
+
+    // Аргументы функции (открывающая скобка уже прочитана)
+    fn function(&mut self, name: &str, pos: usize) -> Result<Expr, QueryError> {
+        match name {
+            "deps" | "rdeps" => {
+                let e = Box::new(self.expr()?);
+                let depth = if self.peek() == Some(&TokenKind::Comma) {
+                    self.pos += 1;
+                    Some(self.number()?)
+                } else {
+                    None
+                };
+                Ok(if name == "deps" { Expr::Deps(e, depth) } else { Expr::Rdeps(e, depth) })
+            }
+            "allpaths" => {
+                let from = Box::new(self.expr()?);
+                self.expect(TokenKind::Comma)?;
+                Ok(Expr::AllPaths(from, Box::new(self.expr()?)))
+            }
+            "attr" => {
+                let name = self.string()?;
+                self.expect(TokenKind::Comma)?;
+                let pattern = self.string()?;
+                self.expect(TokenKind::Comma)?;
+                Ok(Expr::Attr { name, pattern, expr: Box::new(self.expr()?) })
+            }
+            other => Err(syntax(
+                pos,
+                format!("unknown function '{}', expected deps, rdeps, allpaths or attr", other),
+            )),
+        }
+    }
+
+    // Строка в кавычках или слово
+    fn string(&mut self) -> Result<String, QueryError> {
+        let pos = self.here();
+        match self.next()? {
+            TokenKind::Word(s) | TokenKind::Str(s) => Ok(s),
+            other => Err(syntax(pos, format!("expected a string, got {}", other.describe()))),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, QueryError> {
+        let pos = self.here();
+        match self.next()? {
+            TokenKind::Word(w) => w.parse().map_err(|_| syntax(pos, format!("expected a depth, got '{}'", w))),
+            other => Err(syntax(pos, format!("expected a depth, got {}", other.describe()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // app -> lib-a -> core -> util, app -> lib-b -> core, tool -> util
+    fn sample() -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in [
+            ("app", "lib-a"),
+            ("app", "lib-b"),
+            ("lib-a", "core"),
+            ("lib-b", "core"),
+            ("core", "util"),
+            ("tool", "util"),
+        ] {
+            g.add_edge(from, to);
+        }
+        g.set_attribute("lib-a", "license", "MIT");
+        g.set_attribute("lib-b", "license", "GPL-3.0");
+        g.set_version("core", "1.2.0");
+        g
+    }
+
+    fn eval(text: &str) -> Vec<String> {
+        run(&sample(), text).unwrap().into_iter().collect()
+    }
+
+    fn error(text: &str) -> String {
+        parse(text).unwrap_err().to_string()
+    }
+
+    fn pkg(name: &str) -> Box<Expr> {
+        Box::new(Expr::Packages(name.into()))
+    }
+
+    #[test]
+    fn tokenizes_names_operators_and_strings() {
+        // '-' внутри имени - часть имени, отдельно стоящий '-' - оператор
+        assert_eq!(parse("lib-a").unwrap(), Expr::Packages("lib-a".into()));
+        assert_eq!(parse("lib-a - lib-b").unwrap(), Expr::Except(pkg("lib-a"), pkg("lib-b")));
+        assert_eq!(parse("@scope/pkg:1.0").unwrap(), Expr::Packages("@scope/pkg:1.0".into()));
+        assert_eq!(parse(r#""a b\"c""#).unwrap(), Expr::Packages("a b\"c".into()));
+    }
+
+    #[test]
+    fn parses_operators_left_to_right() {
+        assert_eq!(
+            parse("a + b - c ^ d").unwrap(),
+            Expr::Intersect(Box::new(Expr::Except(Box::new(Expr::Union(pkg("a"), pkg("b"))), pkg("c"))), pkg("d"))
+        );
+        assert_eq!(parse("a + (b - c)").unwrap(), Expr::Union(pkg("a"), Box::new(Expr::Except(pkg("b"), pkg("c")))));
+        assert_eq!(parse("a union b except c").unwrap(), parse("a + b - c").unwrap());
+        assert_eq!(parse("a intersect b").unwrap(), parse("a ^ b").unwrap());
+    }
+
+    #[test]
+    fn parses_functions() {
+        assert_eq!(parse("deps(app, 2)").unwrap(), Expr::Deps(pkg("app"), Some(2)));
+        assert_eq!(parse("rdeps(a + b)").unwrap(), Expr::Rdeps(Box::new(Expr::Union(pkg("a"), pkg("b"))), None));
+        assert_eq!(parse("allpaths(a, b)").unwrap(), Expr::AllPaths(pkg("a"), pkg("b")));
+        assert_eq!(
+            parse(r#"attr(license, "M*", deps(app))"#).unwrap(),
+            Expr::Attr { name: "license".into(), pattern: "M*".into(), expr: Box::new(Expr::Deps(pkg("app"), None)) }
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_positions() {
+        assert_eq!(error(""), "syntax error at position 0: unexpected end of query");
+        assert_eq!(error("deps(app"), "syntax error at position 8: unexpected end of query");
+        assert_eq!(error("(a"), "syntax error at position 2: unexpected end of query");
+        assert_eq!(error("a b"), "syntax error at position 2: unexpected 'b'");
+        assert_eq!(error("a + )"), "syntax error at position 4: unexpected ')'");
+        assert_eq!(error("deps(app, x)"), "syntax error at position 10: expected a depth, got 'x'");
+        assert_eq!(error("deps(app; 1)"), "syntax error at position 8: unexpected character ';'");
+        assert_eq!(error("allpaths(a b)"), "syntax error at position 11: expected ',', got 'b'");
+        assert_eq!(error(r#"attr((, "x", a)"#), "syntax error at position 5: expected a string, got '('");
+        assert_eq!(
+            error("  find(a)"),
+            "syntax error at position 2: unknown function 'find', expected deps, rdeps, allpaths or attr"
+        );
+        assert_eq!(error(r#"a + "open"#), "syntax error at position 4: unterminated string");
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("lib-*", "lib-a"));
+        assert!(glob_match("lib-?", "lib-b"));
+        assert!(!glob_match("lib-?", "lib-ab"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "a-x-b-y-b-c"));
+        assert!(!glob_match("a*b*c", "a-x-c-b"));
+        assert!(glob_match("**x", "yyx"));
+        assert!(!glob_match("", "x"));
+        assert!(glob_match("ядро?", "ядро1"));
+    }
+
+    #[test]
+    fn evaluates_closures_with_depth() {
+        assert_eq!(eval("deps(app, 0)"), ["app"]);
+        assert_eq!(eval("deps(app, 1)"), ["app", "lib-a", "lib-b"]);
+        assert_eq!(eval("deps(app)"), ["app", "core", "lib-a", "lib-b", "util"]);
+        assert_eq!(eval("rdeps(util, 1)"), ["core", "tool", "util"]);
+        assert_eq!(eval("rdeps(util, 2)"), ["core", "lib-a", "lib-b", "tool", "util"]);
+        assert_eq!(eval("deps(lib-*, 1)"), ["core", "lib-a", "lib-b"]);
+    }
+
+    #[test]
+    fn evaluates_allpaths_and_set_operators() {
+        assert_eq!(eval("allpaths(app, core)"), ["app", "core", "lib-a", "lib-b"]);
+        assert_eq!(eval("allpaths(lib-a, util)"), ["core", "lib-a", "util"]);
+        assert!(eval("allpaths(tool, core)").is_empty());
+        assert_eq!(eval("deps(app) ^ rdeps(util) - util"), ["app", "core", "lib-a", "lib-b"]);
+        assert_eq!(eval("tool + deps(core) except core"), ["tool", "util"]);
+        assert_eq!(eval("tool + (deps(core) except core)"), ["tool", "util"]);
+        assert_eq!(eval("deps(tool) intersect deps(app)"), ["util"]);
+    }
+
+    #[test]
+    fn filters_by_attributes() {
+        assert_eq!(eval(r#"attr(license, "M*", deps(app))"#), ["lib-a"]);
+        assert_eq!(eval(r#"attr(license, "*", deps(app))"#), ["lib-a", "lib-b"]);
+        assert_eq!(eval(r#"attr(version, "1.*", deps(app))"#), ["core"]);
+        assert_eq!(eval(r#"attr(name, "?ool", "*")"#), ["tool"]);
+    }
+
+    #[test]
+    fn reports_unknown_packages() {
+        let err = run(&sample(), "deps(app) + missing*").unwrap_err();
+        assert_eq!(err.to_string(), "no package matches 'missing*'");
+    }
+}
@@ -0,0 +1,439 @@
+// Модуль с представлениями дерева зависимостей (текст, JSON, HTML) поверх обхода графа
+
+use std::env;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+use terminal_size::Width;
+
+use crate::config::AppConfig;
+use crate::graph::{DependencyGraph, EdgeInfo, EdgeKind, FirstSeen, TreeVisitor};
+use crate::json::JsonValue;
+
+/// Вид текстового дерева
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TreeStyle {
+    /// Отступы в два пробела
+    #[default]
+    Indent,
+    /// Направляющие из символов псевдографики: ├── └── │
+    Unicode,
+    /// Направляющие только из ASCII: |-- `-- |
+    Ascii,
+}
+
+impl FromStr for TreeStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "indent" => Ok(TreeStyle::Indent),
+            "unicode" => Ok(TreeStyle::Unicode),
+            "ascii" => Ok(TreeStyle::Ascii),
+            other => Err(format!("unknown tree style '{other}', expected 'indent', 'unicode' or 'ascii'")),
+        }
+    }
+}
+
+/// Когда раскрашивать текстовое дерево
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Только при выводе в терминал и без переменной NO_COLOR
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            other => Err(format!("unknown color mode '{other}', expected 'auto', 'always' or 'never'")),
+        }
+    }
+}
+
+/// Параметры оформления текстового дерева
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextStyle {
+    pub tree: TreeStyle,
+    /// Номер глубины в начале каждой строки
+    pub depth_prefix: bool,
+    /// ANSI-цвета по типу зависимости и состоянию узла
+    pub color: bool,
+    /// Ширина, до которой обрезаются строки
+    pub width: Option<usize>,
+}
+
+impl TextStyle {
+    /// Оформление для вывода на экран: цвет и ширина по умолчанию берутся из терминала
+    pub fn terminal(cfg: &AppConfig) -> Self {
+        let tty = io::stdout().is_terminal();
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        // Ширина: MaxWidth (--width), затем COLUMNS, затем размер окна терминала
+        let columns = env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.trim().parse().ok())
+            .filter(|&c| c > 0)
+            .or_else(|| terminal_size::terminal_size_of(io::stdout()).map(|(Width(w), _)| usize::from(w)));
+        Self {
+            tree: cfg.tree_style,
+            depth_prefix: cfg.depth_prefix,
+            color: match cfg.color {
+                ColorMode::Auto => tty && !no_color,
+                ColorMode::Always => true,
+                ColorMode::Never => false,
+            },
+            width: cfg.max_width.or(if tty { columns } else { None }),
+        }
+    }
+
+    /// Оформление для записи в файл: цвет только при явном Always, ширина только заданная
+    pub fn file(cfg: &AppConfig) -> Self {
+        Self {
+            tree: cfg.tree_style,
+            depth_prefix: cfg.depth_prefix,
+            color: cfg.color == ColorMode::Always,
+            width: cfg.max_width,
+        }
+    }
+}
+
+// ANSI-коды цветов
+const RED: &str = "31";
+const YELLOW: &str = "33";
+const BLUE: &str = "34";
+const MAGENTA: &str = "35";
+const DIM: &str = "2";
+
+/// Текстовое дерево. Строки копятся до `finish`: направляющие зависят от того,
+/// есть ли у узла следующие братья, а это известно только после обхода
+#[derive(Debug, Default)]
+pub struct TextRenderer {
+    style: TextStyle,
+    lines: Vec<TextLine>,
+    /// Номера строк раскрытых узлов (по порядку событий `enter`) для обратных ссылок
+    entered: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct TextLine {
+    depth: usize,
+    text: String,
+    /// ANSI-код цвета строки
+    paint: Option<&'static str>,
+}
+
+impl TextRenderer {
+    /// Представление с заданным оформлением
+    pub fn new(style: TextStyle) -> Self {
+        Self { style, ..Self::default() }
+    }
+
+    /// Готовый текст
+    pub fn finish(self) -> String {
+        let prefixes = self.prefixes();
+        let depth_width = self.lines.iter().map(|l| l.depth).max().unwrap_or(0).to_string().len();
+        let mut out = String::new();
+        for (line, guide) in self.lines.iter().zip(prefixes) {
+            let mut prefix = String::new();
+            if self.style.depth_prefix {
+                let _ = write!(prefix, "{:>w$} ", line.depth, w = depth_width);
+            }
+            prefix.push_str(&guide);
+
+            let text = match self.style.width {
+                Some(width) => truncate(&line.text, width.saturating_sub(prefix.chars().count())),
+                None => line.text.clone(),
+            };
+            match line.paint.filter(|_| self.style.color) {
+                Some(code) => {
+                    let _ = writeln!(out, "{}\x1b[{}m{}\x1b[0m", prefix, code, text);
+                }
+                None => {
+                    let _ = writeln!(out, "{}{}", prefix, text);
+                }
+            }
+        }
+        out
+    }
+
+    // Отступы или направляющие перед каждой строкой. Проход с конца: `more[k]` - есть ли
+    // ниже строка глубины k в пределах того же родителя
+    fn prefixes(&self) -> Vec<String> {
+        let (tee, elbow, bar) = match self.style.tree {
+            TreeStyle::Indent => return self.lines.iter().map(|l| "  ".repeat(l.depth)).collect(),
+            TreeStyle::Unicode => ("├── ", "└── ", "│   "),
+            TreeStyle::Ascii => ("|-- ", "`-- ", "|   "),
+        };
+        let mut prefixes = vec![String::new(); self.lines.len()];
+        let mut more: Vec<bool> = Vec::new();
+        for (i, line) in self.lines.iter().enumerate().rev() {
+            let d = line.depth;
+            more.resize(d + 1, false);
+            if d > 0 {
+                let prefix = &mut prefixes[i];
+                for &m in &more[1..d] {
+                    prefix.push_str(if m { bar } else { "    " });
+                }
+                prefix.push_str(if more[d] { tee } else { elbow });
+            }
+            more[d] = true;
+        }
+        prefixes
+    }
+
+    fn line(&mut self, depth: usize, name: &str, edge: Option<&EdgeInfo>, suffix: &str, state: Option<&'static str>) {
+        let mut text = name.to_string();
+        // Свёрнутая цепочка показывается рядом с узлом, в который она ведёт
+        if let Some(info) = edge.filter(|i| !i.via.is_empty()) {
+            let _ = write!(text, " ({})", info.via_label());
+        }
+        text.push_str(suffix);
+        let paint = state.or(match edge.map(|e| e.kind) {
+            Some(EdgeKind::Dev) => Some(BLUE),
+            Some(EdgeKind::Build) => Some(MAGENTA),
+            _ => None,
+        });
+        self.lines.push(TextLine { depth, text, paint });
+    }
+}
+
+// Обрезка текста до `width` символов с многоточием в конце
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+impl TreeVisitor for TextRenderer {
+    fn enter(&mut self, name: &str, depth: usize, edge: Option<&EdgeInfo>) {
+        self.line(depth, name, edge, "", None);
+        self.entered.push(self.lines.len());
+    }
+
+    fn cycle(&mut self, name: &str, depth: usize, edge: Option<&EdgeInfo>) {
+        self.line(depth, name, edge, " (cycle)", Some(RED));
+    }
+
+    fn visited(&mut self, name: &str, depth: usize, edge: Option<&EdgeInfo>, first: Option<FirstSeen<'_>>) {
+        let suffix = match first.and_then(|f| Some((self.entered.get(f.index)?, f.parent))) {
+            Some((line, Some(parent))) => format!(" (visited, see line {} under {})", line, parent),
+            Some((line, None)) => format!(" (visited, see line {})", line),
+            None => " (visited)".to_string(),
+        };
+        self.line(depth, name, edge, &suffix, Some(DIM));
+    }
+
+    // Отброшенные фильтром пакеты показываются только в деревьях с направляющими
+    fn filtered(&mut self, name: &str, depth: usize) {
+        if self.style.tree != TreeStyle::Indent {
+            self.line(depth, name, None, " (filtered)", Some(YELLOW));
+        }
+    }
+
+    fn cut(&mut self, depth: usize, reason: &str) {
+        self.lines.push(TextLine { depth, text: format!("… ({})", reason), paint: Some(DIM) });
+    }
+}
+
+/// Узлы по уровням обхода в ширину: "Level N: a, b, c"
+pub fn render_levels(levels: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    for (depth, names) in levels.iter().enumerate() {
+        let _ = writeln!(out, "Level {}: {}", depth, names.join(", "));
+    }
+    out
+}
+
+/// Дерево в виде вложенных JSON-объектов
+#[derive(Debug)]
+pub struct JsonRenderer<'a> {
+    graph: &'a DependencyGraph,
+    /// Открытые узлы текущего пути
+    stack: Vec<JsonFrame>,
+    root: Option<JsonValue>,
+}
+
+// Раскрываемый узел: его поля и уже построенные потомки
+#[derive(Debug)]
+struct JsonFrame {
+    fields: Vec<(String, JsonValue)>,
+    children: Vec<JsonValue>,
+}
+
+impl<'a> JsonRenderer<'a> {
+    /// Представление для дерева графа `graph` (из него берутся версии пакетов)
+    pub fn new(graph: &'a DependencyGraph) -> Self {
+        Self { graph, stack: Vec::new(), root: None }
+    }
+
+    /// Корневой узел дерева (null, если корень отброшен фильтром)
+    pub fn finish(self) -> JsonValue {
+        self.root.unwrap_or(JsonValue::Null)
+    }
+
+    // Поля узла: имя, версия и сведения о ребре, по которому он достигнут
+    fn fields(&self, name: &str, edge: Option<&EdgeInfo>, status: &str) -> Vec<(String, JsonValue)> {
+        let mut fields = vec![("name".to_string(), name.into())];
+        if let Some(v) = self.graph.version(name) {
+            fields.push(("version".into(), v.into()));
+        }
+        if let Some(info) = edge {
+            fields.push(("kind".into(), info.kind.as_str().into()));
+            if !info.via.is_empty() {
+                fields.push(("via".into(), JsonValue::strings(&info.via)));
+            }
+        }
+        fields.push(("status".into(), status.into()));
+        fields
+    }
+
+    fn push_leaf(&mut self, value: JsonValue) {
+        match self.stack.last_mut() {
+            Some(frame) => frame.children.push(value),
+            None => self.root = Some(value),
+        }
+    }
+}
+
+impl TreeVisitor for JsonRenderer<'_> {
+    fn enter(&mut self, name: &str, _depth: usize, edge: Option<&EdgeInfo>) {
+        let fields = self.fields(name, edge, "expanded");
+        self.stack.push(JsonFrame { fields, children: Vec::new() });
+    }
+
+    fn leave(&mut self, _name: &str, _depth: usize) {
+        if let Some(mut frame) = self.stack.pop() {
+            frame.fields.push(("children".into(), JsonValue::Array(frame.children)));
+            self.push_leaf(JsonValue::Object(frame.fields));
+        }
+    }
+
+    fn cycle(&mut self, name: &str, _depth: usize, edge: Option<&EdgeInfo>) {
+        let leaf = JsonValue::Object(self.fields(name, edge, "cycle"));
+        self.push_leaf(leaf);
+    }
+
+    fn visited(&mut self, name: &str, _depth: usize, edge: Option<&EdgeInfo>, first: Option<FirstSeen<'_>>) {
+        let mut fields = self.fields(name, edge, "visited");
+        if let Some(first) = first {
+            let first = JsonValue::object(vec![("index", first.index.into()), ("parent", first.parent.into())]);
+            fields.push(("first_seen".into(), first));
+        }
+        self.push_leaf(JsonValue::Object(fields));
+    }
+
+    fn cut(&mut self, _depth: usize, reason: &str) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.fields.push(("truncated".into(), reason.into()));
+        }
+    }
+}
+
+/// Дерево в виде HTML-страницы со вложенными списками. Раскрытые узлы получают якоря
+/// `n<номер>`, на которые ссылаются их повторы
+#[derive(Debug, Default)]
+pub struct HtmlRenderer {
+    out: String,
+    /// Для каждого открытого узла: открыт ли уже список его потомков
+    open: Vec<bool>,
+    /// Число раскрытых узлов
+    entered: usize,
+}
+
+impl HtmlRenderer {
+    /// Готовая HTML-страница с заголовком `title`
+    pub fn finish(self, title: &str) -> String {
+        let title = escape_html(title);
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+             body {{ font-family: monospace; }}\n\
+             ul {{ list-style: none; padding-left: 1.5em; }}\n\
+             .note {{ color: #888; }}\n\
+             .cycle {{ color: #c00; }}\n\
+             a {{ color: inherit; }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n<ul>\n{}</ul>\n</body>\n</html>\n",
+            self.out
+        )
+    }
+
+    // Открытие списка потомков у текущего узла перед первым из них
+    fn child(&mut self) {
+        if let Some(open) = self.open.last_mut()
+            && !*open
+        {
+            *open = true;
+            self.out.push_str("<ul>\n");
+        }
+    }
+
+    fn item(&mut self, name: &str, edge: Option<&EdgeInfo>, class: Option<&str>, note: Option<&str>) {
+        self.child();
+        match class {
+            Some(class) => {
+                let _ = write!(self.out, "<li class=\"{}\">{}", class, escape_html(name));
+            }
+            None => {
+                let _ = write!(self.out, "<li id=\"n{}\">{}", self.entered, escape_html(name));
+            }
+        }
+        if let Some(info) = edge.filter(|i| !i.via.is_empty()) {
+            let _ = write!(self.out, " <span class=\"note\">({})</span>", escape_html(&info.via_label()));
+        }
+        if let Some(note) = note {
+            let _ = write!(self.out, " <span class=\"note\">({})</span>", note);
+        }
+    }
+}
+
+impl TreeVisitor for HtmlRenderer {
+    fn enter(&mut self, name: &str, _depth: usize, edge: Option<&EdgeInfo>) {
+        self.item(name, edge, None, None);
+        self.open.push(false);
+        self.entered += 1;
+    }
+
+    fn leave(&mut self, _name: &str, _depth: usize) {
+        if self.open.pop() == Some(true) {
+            self.out.push_str("</ul>\n");
+        }
+        self.out.push_str("</li>\n");
+    }
+
+    fn cycle(&mut self, name: &str, _depth: usize, edge: Option<&EdgeInfo>) {
+        self.item(name, edge, Some("cycle"), Some("cycle"));
+        self.out.push_str("</li>\n");
+    }
+
+    fn visited(&mut self, name: &str, _depth: usize, edge: Option<&EdgeInfo>, first: Option<FirstSeen<'_>>) {
+        let note = match first {
+            Some(FirstSeen { index, parent: Some(parent) }) => {
+                format!("<a href=\"#n{}\">visited, see under {}</a>", index, escape_html(parent))
+            }
+            Some(FirstSeen { index, parent: None }) => format!("<a href=\"#n{}\">visited, see above</a>", index),
+            None => "visited".to_string(),
+        };
+        self.item(name, edge, Some("note"), Some(&note));
+        self.out.push_str("</li>\n");
+    }
+
+    fn cut(&mut self, _depth: usize, reason: &str) {
+        self.child();
+        let _ = writeln!(self.out, "<li class=\"note\">… ({})</li>", escape_html(reason));
+    }
+}
+
+// Экранирование спецсимволов HTML
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
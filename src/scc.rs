@@ -0,0 +1,369 @@
+// Модуль для поиска компонент сильной связности, циклов и построения конденсации графа
+
+use std::collections::{HashMap, HashSet};
+
+use crate::graph::DependencyGraph;
+
+/// Граф в виде списков смежности по индексам узлов (узлы упорядочены по имени)
+pub struct IndexedGraph<'a> {
+    pub names: Vec<&'a str>,
+    pub adj: Vec<Vec<usize>>,
+}
+
+impl<'a> IndexedGraph<'a> {
+    /// Построение индексированного представления графа
+    pub fn new(graph: &'a DependencyGraph) -> Self {
+        let names = graph.node_names();
+        let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+        let adj = names
+            .iter()
+            .map(|n| graph.dependencies(n).filter_map(|d| index.get(d).copied()).collect())
+            .collect();
+        Self { names, adj }
+    }
+}
+
+/// Компоненты сильной связности (алгоритм Тарьяна, итеративная версия).
+/// Компоненты возвращаются в обратном топологическом порядке: зависимости раньше зависящих.
+pub fn strongly_connected_components(graph: &DependencyGraph) -> Vec<Vec<String>> {
+    let g = IndexedGraph::new(graph);
+    tarjan(&g.adj)
+        .into_iter()
+        .map(|comp| {
+            let mut names: Vec<String> = comp.into_iter().map(|i| g.names[i].to_string()).collect();
+            names.sort();
+            names
+        })
+        .collect()
+}
+
+/// Алгоритм Тарьяна над списками смежности
+pub fn tarjan(adj: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = adj.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut counter = 0;
+
+    for start in 0..n {
+        if index[start] != UNVISITED {
+            continue;
+        }
+        // Явный стек вызовов: (узел, номер следующего потомка)
+        let mut call: Vec<(usize, usize)> = vec![(start, 0)];
+        index[start] = counter;
+        low[start] = counter;
+        counter += 1;
+        stack.push(start);
+        on_stack[start] = true;
+
+        while let Some(frame) = call.last_mut() {
+            let v = frame.0;
+            if frame.1 < adj[v].len() {
+                let w = adj[v][frame.1];
+                frame.1 += 1;
+                if index[w] == UNVISITED {
+                    index[w] = counter;
+                    low[w] = counter;
+                    counter += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+
+            call.pop();
+            if let Some(&(parent, _)) = call.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut comp = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    comp.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                components.push(comp);
+            }
+        }
+    }
+
+    components
+}
+
+/// Результат поиска циклов
+#[derive(Debug, Default)]
+pub struct CycleReport {
+    /// Нетривиальные компоненты сильной связности (с циклами)
+    pub components: Vec<Vec<String>>,
+    /// Элементарные циклы в виде путей A -> ... -> A
+    pub cycles: Vec<Vec<String>>,
+    /// Перечисление остановлено по лимиту количества циклов
+    pub truncated: bool,
+}
+
+/// Поиск всех элементарных циклов длиной не больше `max_len` (не более `max_cycles` штук)
+pub fn find_cycles(graph: &DependencyGraph, max_len: Option<usize>, max_cycles: usize) -> CycleReport {
+    let g = IndexedGraph::new(graph);
+    let mut report = CycleReport::default();
+
+    let mut components: Vec<Vec<usize>> = tarjan(&g.adj)
+        .into_iter()
+        .filter(|c| c.len() > 1 || g.adj[c[0]].contains(&c[0]))
+        .collect();
+    for c in &mut components {
+        c.sort();
+    }
+    components.sort();
+
+    // Обратные списки смежности и рабочие массивы, общие для всех стартовых узлов
+    let mut radj = vec![Vec::new(); g.names.len()];
+    for (v, deps) in g.adj.iter().enumerate() {
+        for &w in deps {
+            radj[w].push(v);
+        }
+    }
+    let mut allowed = vec![false; g.names.len()];
+    let mut mark = vec![false; g.names.len()];
+    let mut in_scope = vec![false; g.names.len()];
+    let mut on_path = vec![false; g.names.len()];
+
+    for comp in &components {
+        report.components.push(comp.iter().map(|&i| g.names[i].to_string()).collect());
+
+        // Каждый цикл перечисляется один раз - от его наименьшего узла
+        for &i in comp {
+            allowed[i] = true;
+        }
+        for &start in comp {
+            let scope = cycle_scope(&g.adj, &radj, start, &allowed, &mut mark);
+            for &v in &scope {
+                in_scope[v] = true;
+            }
+            let mut found = Vec::new();
+            let budget = max_cycles - report.cycles.len();
+            let complete = cycles_from(&g.adj, start, &in_scope, max_len, budget, &mut on_path, &mut found);
+            for &v in &scope {
+                in_scope[v] = false;
+            }
+            report.cycles.extend(
+                found
+                    .into_iter()
+                    .map(|c| c.into_iter().map(|i| g.names[i].to_string()).collect()),
+            );
+            if !complete {
+                report.truncated = true;
+                return report;
+            }
+            allowed[start] = false;
+        }
+    }
+
+    report
+}
+
+// Узлы, через которые могут проходить циклы из `start`: из разрешённых узлов, ведущих в `start`,
+// остаются достижимые из него. Сначала идёт обратный обход, поэтому для узла, в который уже
+// не ведёт ни один разрешённый узел (например, в кольце после первого старта), поиск стоит O(1)
+fn cycle_scope(
+    adj: &[Vec<usize>],
+    radj: &[Vec<usize>],
+    start: usize,
+    allowed: &[bool],
+    mark: &mut [bool],
+) -> Vec<usize> {
+    let mut reaching = vec![start];
+    mark[start] = true;
+    let mut stack = vec![start];
+    while let Some(v) = stack.pop() {
+        for &u in &radj[v] {
+            if allowed[u] && !mark[u] {
+                mark[u] = true;
+                reaching.push(u);
+                stack.push(u);
+            }
+        }
+    }
+
+    let mut scope = vec![start];
+    let mut seen = HashSet::from([start]);
+    stack.push(start);
+    while let Some(v) = stack.pop() {
+        for &w in &adj[v] {
+            if mark[w] && seen.insert(w) {
+                scope.push(w);
+                stack.push(w);
+            }
+        }
+    }
+    for v in reaching {
+        mark[v] = false;
+    }
+    scope
+}
+
+// Поиск в глубину путей, возвращающихся в `start`, с явным стеком: длинный цикл не переполняет
+// стек вызовов; false, если достигнут лимит количества. `on_path` возвращается очищенным
+fn cycles_from(
+    adj: &[Vec<usize>],
+    start: usize,
+    allowed: &[bool],
+    max_len: Option<usize>,
+    budget: usize,
+    on_path: &mut [bool],
+    found: &mut Vec<Vec<usize>>,
+) -> bool {
+    let mut path = vec![start];
+    on_path[start] = true;
+    // Номер следующего потомка для каждого узла пути
+    let mut next = vec![0];
+    while let Some(i) = next.last_mut() {
+        let v = *path.last().unwrap();
+        let Some(&w) = adj[v].get(*i) else {
+            next.pop();
+            on_path[v] = false;
+            path.pop();
+            continue;
+        };
+        *i += 1;
+        if !allowed[w] {
+            continue;
+        }
+        if w == start {
+            if found.len() >= budget {
+                for &v in &path {
+                    on_path[v] = false;
+                }
+                return false;
+            }
+            let mut cycle = path.clone();
+            cycle.push(start);
+            found.push(cycle);
+        } else if !on_path[w] && max_len.is_none_or(|m| path.len() < m) {
+            path.push(w);
+            on_path[w] = true;
+            next.push(0);
+        }
+    }
+    true
+}
+
+/// Конденсация: граф, в котором каждая компонента сильной связности свёрнута в один узел
+pub struct Condensation {
+    pub graph: DependencyGraph,
+    /// Имя узла-компоненты для каждого исходного узла
+    pub component_of: HashMap<String, String>,
+}
+
+/// Построение конденсации графа
+pub fn condense(graph: &DependencyGraph) -> Condensation {
+    let mut component_of = HashMap::new();
+    let mut out = DependencyGraph::new();
+
+    for comp in strongly_connected_components(graph) {
+        let name = if comp.len() == 1 {
+            comp[0].clone()
+        } else {
+            format!("{{{}}}", comp.join(", "))
+        };
+        out.ensure_node(&name);
+        if comp.len() == 1 {
+            if let Some(v) = graph.version(&name) {
+                out.set_version(&name, v);
+            }
+            for (key, value) in graph.attributes(&name).into_iter().flatten() {
+                out.set_attribute(&name, key, value);
+            }
+        }
+        for member in comp {
+            component_of.insert(member, name.clone());
+        }
+    }
+
+    for name in graph.node_names() {
+        let from = &component_of[name];
+        for dep in graph.dependencies(name) {
+            let to = &component_of[dep];
+            if from != to {
+                out.add_edge(from, to);
+            }
+        }
+    }
+
+    Condensation { graph: out, component_of }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in edges {
+            g.add_edge(from, to);
+        }
+        g
+    }
+
+    fn cycles(report: &CycleReport) -> Vec<String> {
+        report.cycles.iter().map(|c| c.join(" ")).collect()
+    }
+
+    // Два цикла через A (A-B-A и A-B-C-A), петля у D и ацикличный хвост E
+    fn sample() -> DependencyGraph {
+        graph(&[("A", "B"), ("B", "A"), ("B", "C"), ("C", "A"), ("C", "D"), ("D", "D"), ("D", "E")])
+    }
+
+    #[test]
+    fn finds_cycles_and_self_loops() {
+        let report = find_cycles(&sample(), None, 100);
+        assert_eq!(report.components, [vec!["A", "B", "C"], vec!["D"]]);
+        assert_eq!(cycles(&report), ["A B A", "A B C A", "D D"]);
+        assert!(!report.truncated);
+        assert!(find_cycles(&graph(&[("A", "B"), ("B", "C")]), None, 100).components.is_empty());
+    }
+
+    #[test]
+    fn limits_cycle_length_and_count() {
+        let report = find_cycles(&sample(), Some(2), 100);
+        assert_eq!(cycles(&report), ["A B A", "D D"]);
+        let report = find_cycles(&sample(), None, 2);
+        assert_eq!(cycles(&report), ["A B A", "A B C A"]);
+        assert!(report.truncated);
+    }
+
+    #[test]
+    fn long_ring_does_not_overflow() {
+        let names: Vec<String> = (0..100_000).map(|i| format!("n{i:06}")).collect();
+        let mut g = DependencyGraph::new();
+        for (i, name) in names.iter().enumerate() {
+            g.add_edge(name, &names[(i + 1) % names.len()]);
+        }
+        let report = find_cycles(&g, None, 10);
+        assert_eq!(report.components.len(), 1);
+        assert_eq!(report.cycles.len(), 1);
+        assert_eq!(report.cycles[0].len(), 100_001);
+    }
+
+    #[test]
+    fn condenses_components_into_named_nodes() {
+        let mut g = sample();
+        g.set_version("E", "1.0");
+        let c = condense(&g);
+        assert_eq!(c.graph.node_names(), ["D", "E", "{A, B, C}"]);
+        assert_eq!(c.component_of["B"], "{A, B, C}");
+        assert_eq!(c.component_of["D"], "D");
+        assert_eq!(c.graph.dependencies("{A, B, C}").collect::<Vec<_>>(), ["D"]);
+        // Петля внутри компоненты из одного узла не переносится в конденсацию
+        assert_eq!(c.graph.dependencies("D").collect::<Vec<_>>(), ["E"]);
+        assert_eq!(c.graph.version("E"), Some("1.0"));
+    }
+}
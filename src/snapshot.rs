@@ -0,0 +1,363 @@
+// Модуль для сохранения графа в версионированный JSON-снимок и загрузки из него
+
+use std::fs;
+
+use thiserror::Error;
+
+use crate::child_order::ChildOrder;
+use crate::config::AppConfig;
+use crate::d2::D2Theme;
+use crate::graph::{Dedupe, DependencyGraph, EdgeInfo};
+use crate::json::{self, JsonValue};
+use crate::render::{ColorMode, TreeStyle};
+use crate::source::Mode;
+
+/// Идентификатор формата снимка
+pub const FORMAT: &str = "depgraph-snapshot";
+
+/// Текущая версия формата снимка
+pub const VERSION: usize = 1;
+
+/// Перечисление возможных ошибок при работе со снимком
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("cannot read snapshot: {0}")]
+    Read(String),
+
+    #[error("cannot write snapshot: {0}")]
+    Write(String),
+
+    #[error("malformed JSON: {0}")]
+    Json(String),
+
+    #[error("unsupported snapshot version {0} (supported: {VERSION})")]
+    Version(usize),
+
+    #[error("invalid snapshot: {0}")]
+    Format(String),
+}
+
+/// Граф вместе со сведениями об источнике и конфигурации, из которых он получен
+#[derive(Debug)]
+pub struct Snapshot {
+    pub graph: DependencyGraph,
+    /// Параметры конфигурации, с которыми граф был построен (без <Output>, <Transforms>
+    /// и параметров оформления вывода)
+    pub config: AppConfig,
+    /// Описание источника
+    pub description: String,
+    /// Построен ли полный (транзитивный) граф
+    pub full_graph: bool,
+}
+
+/// Сохранение графа в файл снимка
+pub fn save(
+    path: &str,
+    graph: &DependencyGraph,
+    cfg: &AppConfig,
+    description: &str,
+    full_graph: bool,
+) -> Result<(), SnapshotError> {
+    let text = to_json(graph, cfg, description, full_graph).to_pretty_string();
+    fs::write(path, text + "\n").map_err(|e| SnapshotError::Write(format!("{}: {}", path, e)))
+}
+
+/// Загрузка снимка из файла
+pub fn load(path: &str) -> Result<Snapshot, SnapshotError> {
+    let text = fs::read_to_string(path).map_err(|e| SnapshotError::Read(format!("{}: {}", path, e)))?;
+    let value = json::parse(&text).map_err(SnapshotError::Json)?;
+    from_json(&value)
+}
+
+/// Представление снимка в JSON
+pub fn to_json(graph: &DependencyGraph, cfg: &AppConfig, description: &str, full_graph: bool) -> JsonValue {
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for name in graph.node_names() {
+        let attributes = graph
+            .attributes(name)
+            .into_iter()
+            .flatten()
+            .map(|(k, v)| (k.clone(), JsonValue::String(v.clone())))
+            .collect();
+        nodes.push(JsonValue::object(vec![
+            ("name", name.into()),
+            ("version", graph.version(name).into()),
+            ("attributes", JsonValue::Object(attributes)),
+            ("truncated", graph.truncated.get(name).copied().into()),
+        ]));
+
+        // Рёбра пишутся в порядке объявления зависимостей
+        for dep in graph.dependencies(name) {
+            let mut fields = vec![("from", name.into()), ("to", dep.into())];
+            if let Some(info) = graph.edge_info(name, dep) {
+                fields.push(("kind", info.kind.as_str().into()));
+                fields.push(("optional", info.optional.into()));
+                fields.push(("features", JsonValue::strings(&info.features)));
+                fields.push(("via", JsonValue::strings(&info.via)));
+            }
+            edges.push(JsonValue::object(fields));
+        }
+    }
+
+    JsonValue::object(vec![
+        ("format", FORMAT.into()),
+        ("version", VERSION.into()),
+        ("generator", concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")).into()),
+        (
+            "source",
+            JsonValue::object(vec![
+                ("mode", cfg.mode.as_str().into()),
+                ("repo_source", cfg.repo_source.as_str().into()),
+                ("description", description.into()),
+                ("full_graph", full_graph.into()),
+            ]),
+        ),
+        (
+            "config",
+            JsonValue::object(vec![
+                ("package_name", cfg.package_name.as_str().into()),
+                ("ascii_tree", cfg.ascii_tree.into()),
+                ("exclude_filter", cfg.exclude_filter.as_str().into()),
+                ("max_depth", cfg.max_depth.into()),
+                ("max_nodes", cfg.max_nodes.into()),
+            ]),
+        ),
+        ("nodes", JsonValue::Array(nodes)),
+        ("edges", JsonValue::Array(edges)),
+    ])
+}
+
+/// Восстановление снимка из JSON
+pub fn from_json(value: &JsonValue) -> Result<Snapshot, SnapshotError> {
+    if value.get("format").and_then(JsonValue::as_str) != Some(FORMAT) {
+        return Err(SnapshotError::Format(format!("'format' must be \"{}\"", FORMAT)));
+    }
+    let version = value.get("version").and_then(JsonValue::as_f64).ok_or_else(|| missing("version"))?;
+    if version < 0.0 || version.fract() != 0.0 {
+        return Err(SnapshotError::Format(format!("'version' must be a non-negative integer, got {}", version)));
+    }
+    if version != VERSION as f64 {
+        return Err(SnapshotError::Version(version as usize));
+    }
+
+    let source = value.get("source").ok_or_else(|| missing("source"))?;
+    let config = value.get("config").ok_or_else(|| missing("config"))?;
+    let mode: Mode = string(source, "mode")?.parse().map_err(SnapshotError::Format)?;
+    let cfg = AppConfig {
+        package_name: string(config, "package_name")?.to_string(),
+        repo_source: string(source, "repo_source")?.to_string(),
+        mode,
+        ascii_tree: optional_bool(config, "ascii_tree")?.unwrap_or(true),
+        exclude_filter: config.get("exclude_filter").and_then(JsonValue::as_str).unwrap_or_default().to_string(),
+        max_depth: optional_usize(config, "max_depth")?,
+        max_nodes: optional_usize(config, "max_nodes")?,
+        outputs: Vec::new(),
+        transforms: Vec::new(),
+        dedupe: Dedupe::default(),
+        child_order: ChildOrder::default(),
+        tree_style: TreeStyle::default(),
+        depth_prefix: false,
+        color: ColorMode::default(),
+        max_width: None,
+        strict: false,
+        d2_theme: D2Theme::default(),
+    };
+
+    let mut graph = DependencyGraph::new();
+    for node in array(value, "nodes")? {
+        let name = string(node, "name")?;
+        graph.ensure_node(name);
+        if let Some(v) = node.get("version").and_then(JsonValue::as_str) {
+            graph.set_version(name, v);
+        }
+        if let Some(JsonValue::Object(attrs)) = node.get("attributes") {
+            for (key, v) in attrs {
+                let v = v.as_str().ok_or_else(|| invalid(&format!("attribute '{}' of '{}'", key, name)))?;
+                graph.set_attribute(name, key, v);
+            }
+        }
+        if let Some(reason) = node.get("truncated").filter(|v| !v.is_null()) {
+            let reason = match reason.as_str() {
+                Some("depth limit") => "depth limit",
+                Some("node limit") => "node limit",
+                _ => return Err(invalid(&format!("truncation reason of '{}'", name))),
+            };
+            graph.truncated.insert(name.to_string(), reason);
+        }
+    }
+
+    for edge in array(value, "edges")? {
+        let (from, to) = (string(edge, "from")?, string(edge, "to")?);
+        for end in [from, to] {
+            if !graph.contains(end) {
+                return Err(SnapshotError::Format(format!("edge refers to unknown node '{}'", end)));
+            }
+        }
+        match edge.get("kind") {
+            Some(kind) => {
+                let info = EdgeInfo {
+                    kind: kind.as_str().and_then(|k| k.parse().ok()).ok_or_else(|| invalid("edge kind"))?,
+                    optional: optional_bool(edge, "optional")?.unwrap_or(false),
+                    features: strings(edge, "features")?,
+                    via: strings(edge, "via")?,
+                };
+                graph.add_edge_with(from, to, info);
+            }
+            None => graph.add_edge(from, to),
+        }
+    }
+
+    Ok(Snapshot {
+        graph,
+        config: cfg,
+        description: source.get("description").and_then(JsonValue::as_str).unwrap_or("snapshot").to_string(),
+        full_graph: optional_bool(source, "full_graph")?.unwrap_or(true),
+    })
+}
+
+fn missing(field: &str) -> SnapshotError {
+    SnapshotError::Format(format!("missing field '{}'", field))
+}
+
+fn invalid(what: &str) -> SnapshotError {
+    SnapshotError::Format(format!("invalid {}", what))
+}
+
+fn string<'a>(value: &'a JsonValue, field: &str) -> Result<&'a str, SnapshotError> {
+    value.get(field).and_then(JsonValue::as_str).ok_or_else(|| missing(field))
+}
+
+fn array<'a>(value: &'a JsonValue, field: &str) -> Result<&'a [JsonValue], SnapshotError> {
+    value.get(field).and_then(JsonValue::as_array).ok_or_else(|| missing(field))
+}
+
+// Необязательный массив строк (отсутствующее поле - пустой список)
+fn strings(value: &JsonValue, field: &str) -> Result<Vec<String>, SnapshotError> {
+    let Some(items) = value.get(field) else { return Ok(Vec::new()) };
+    items
+        .as_array()
+        .ok_or_else(|| invalid(field))?
+        .iter()
+        .map(|v| v.as_str().map(str::to_string).ok_or_else(|| invalid(field)))
+        .collect()
+}
+
+// Необязательное булево поле: отсутствующее поле - None, значение другого типа - ошибка
+fn optional_bool(value: &JsonValue, field: &str) -> Result<Option<bool>, SnapshotError> {
+    match value.get(field) {
+        None => Ok(None),
+        Some(v) => v.as_bool().map(Some).ok_or_else(|| invalid(field)),
+    }
+}
+
+fn optional_usize(value: &JsonValue, field: &str) -> Result<Option<usize>, SnapshotError> {
+    match value.get(field) {
+        None | Some(JsonValue::Null) => Ok(None),
+        Some(v) => match v.as_f64() {
+            Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(Some(n as usize)),
+            _ => Err(invalid(field)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::graph::EdgeKind;
+
+    const CONFIG: &str = "<Config><PackageName>app</PackageName><RepoSource>repo.txt</RepoSource>\
+        <Mode>test</Mode><AsciiTree>true</AsciiTree><ExcludeFilter>tmp</ExcludeFilter>\
+        <MaxDepth>3</MaxDepth></Config>";
+
+    fn sample() -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        g.set_version("app", "1.0.0");
+        g.set_attribute("app", "license", "MIT OR Apache-2.0");
+        g.set_attribute("app", "note", "quote \" and \\ and\nnewline, ünicode");
+        g.add_edge("app", "core");
+        g.add_edge_with(
+            "app",
+            "serde@1.0",
+            EdgeInfo { kind: EdgeKind::Normal, optional: true, features: vec!["derive".into(), "std".into()], via: vec![] },
+        );
+        g.add_edge_with("app", "test-utils", EdgeInfo { kind: EdgeKind::Dev, ..EdgeInfo::default() });
+        let via = vec!["a".into(), "b".into()];
+        g.add_edge_with("core", "log", EdgeInfo { kind: EdgeKind::Build, via, ..EdgeInfo::default() });
+        g.add_edge("log", "core");
+        g.add_edge("log", "log");
+        g.ensure_node("lonely");
+        g.truncated.insert("serde@1.0".into(), "depth limit");
+        g
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let cfg = config::parse_xml(CONFIG).unwrap();
+        let graph = sample();
+        let text = to_json(&graph, &cfg, "TEST mode", true).to_pretty_string();
+        let snapshot = from_json(&json::parse(&text).unwrap()).unwrap();
+
+        assert_eq!(snapshot.graph.describe(), graph.describe());
+        assert_eq!(snapshot.graph.truncated, graph.truncated);
+        assert_eq!(snapshot.config.package_name, "app");
+        assert_eq!(snapshot.config.repo_source, "repo.txt");
+        assert_eq!(snapshot.config.mode.as_str(), "test");
+        assert_eq!(snapshot.config.exclude_filter, "tmp");
+        assert_eq!(snapshot.config.max_depth, Some(3));
+        assert_eq!(snapshot.description, "TEST mode");
+        assert!(snapshot.full_graph);
+    }
+
+    #[test]
+    fn round_trips_through_file() {
+        let cfg = config::parse_xml(CONFIG).unwrap();
+        let graph = sample();
+        let path = std::env::temp_dir().join(format!("snapshot-test-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        save(path, &graph, &cfg, "TEST mode", false).unwrap();
+        let snapshot = load(path);
+        let _ = fs::remove_file(path);
+        let snapshot = snapshot.unwrap();
+
+        assert_eq!(snapshot.graph.describe(), graph.describe());
+        assert!(!snapshot.full_graph);
+    }
+
+    #[test]
+    fn rejects_other_versions_and_unknown_nodes() {
+        let cfg = config::parse_xml(CONFIG).unwrap();
+        let mut value = to_json(&sample(), &cfg, "TEST mode", true);
+        if let JsonValue::Object(fields) = &mut value {
+            fields.iter_mut().find(|(k, _)| k == "version").unwrap().1 = JsonValue::Number(2.0);
+        }
+        assert!(matches!(from_json(&value), Err(SnapshotError::Version(2))));
+
+        let text = r#"{"format": "depgraph-snapshot", "version": 1,
+            "source": {"mode": "test", "repo_source": "r"}, "config": {"package_name": "A"},
+            "nodes": [{"name": "A"}], "edges": [{"from": "A", "to": "B"}]}"#;
+        let err = from_json(&json::parse(text).unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "invalid snapshot: edge refers to unknown node 'B'");
+    }
+
+    #[test]
+    fn rejects_fractional_versions_and_non_bool_flags() {
+        let text = |version: &str, optional: &str| {
+            format!(
+                r#"{{"format": "depgraph-snapshot", "version": {version},
+                "source": {{"mode": "test", "repo_source": "r"}}, "config": {{"package_name": "A"}},
+                "nodes": [{{"name": "A"}}, {{"name": "B"}}],
+                "edges": [{{"from": "A", "to": "B", "kind": "normal", "optional": {optional}}}]}}"#
+            )
+        };
+        let load_text = |t: String| from_json(&json::parse(&t).unwrap());
+        assert!(load_text(text("1", "true")).unwrap().graph.edge_info("A", "B").unwrap().optional);
+        let err = load_text(text("1.5", "true")).unwrap_err();
+        assert_eq!(err.to_string(), "invalid snapshot: 'version' must be a non-negative integer, got 1.5");
+        let err = load_text(text("-1", "true")).unwrap_err();
+        assert_eq!(err.to_string(), "invalid snapshot: 'version' must be a non-negative integer, got -1");
+        let err = load_text(text("1", "\"yes\"")).unwrap_err();
+        assert_eq!(err.to_string(), "invalid snapshot: invalid optional");
+    }
+}
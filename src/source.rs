@@ -0,0 +1,208 @@
+// Модуль с источниками зависимостей: каждый источник строит DependencyGraph
+
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::cargo_parser::{self, CargoParseError, ManifestDependency};
+use crate::config::AppConfig;
+use crate::graph::DependencyGraph;
+use crate::lockfile::{self, LockfileError};
+use crate::test_repo::{self, TestRepoError};
+
+/// Перечисление возможных ошибок при получении графа из источника
+#[derive(Debug, Error)]
+pub enum SourceError {
+    #[error(transparent)]
+    Cargo(#[from] CargoParseError),
+
+    #[error(transparent)]
+    TestRepo(#[from] TestRepoError),
+
+    #[error(transparent)]
+    Lockfile(#[from] LockfileError),
+}
+
+/// Источник зависимостей, способный построить граф по конфигурации
+pub trait DependencySource {
+    /// Краткое описание источника для вывода пользователю
+    fn description(&self) -> &'static str;
+
+    /// Строит ли источник полный (транзитивный) граф, а не только прямые зависимости
+    fn is_full_graph(&self) -> bool;
+
+    /// Построение графа зависимостей
+    fn load(&self, cfg: &AppConfig) -> Result<DependencyGraph, SourceError>;
+}
+
+/// Конструктор источника
+type SourceFactory = fn() -> Box<dyn DependencySource>;
+
+/// Реестр источников: имя режима в `<Mode>` и конструктор. Новый источник достаточно
+/// добавить сюда, разбор конфигурации от списка режимов не зависит
+const SOURCES: [(&str, SourceFactory); 4] = [
+    ("test", || Box::new(TestRepoSource)),
+    ("real", || Box::new(RemoteManifestSource)),
+    ("local", || Box::new(LocalManifestSource)),
+    ("lockfile", || Box::new(LockfileSource)),
+];
+
+/// Режим работы: источник зависимостей из реестра, заданный в `<Mode>` по имени
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Mode(usize);
+
+impl Mode {
+    /// Имя режима в конфигурационном файле
+    pub fn as_str(&self) -> &'static str {
+        SOURCES[self.0].0
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SOURCES.iter().position(|(name, _)| *name == s.trim()).map(Mode).ok_or_else(|| {
+            let names: Vec<String> = SOURCES.iter().map(|(name, _)| format!("'{}'", name)).collect();
+            format!("unknown mode '{}', expected one of {}", s.trim(), names.join(", "))
+        })
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+/// Источник для заданного режима
+pub fn source_for(mode: Mode) -> Box<dyn DependencySource> {
+    (SOURCES[mode.0].1)()
+}
+
+/// Текстовый файл тестового репозитория
+pub struct TestRepoSource;
+
+impl DependencySource for TestRepoSource {
+    fn description(&self) -> &'static str {
+        "TEST mode (text file parsing)"
+    }
+
+    fn is_full_graph(&self) -> bool {
+        true
+    }
+
+    fn load(&self, cfg: &AppConfig) -> Result<DependencyGraph, SourceError> {
+        let repo = test_repo::load_test_repo(&cfg.repo_source, cfg.strict)?;
+        for warning in &repo.warnings {
+            eprintln!("TEST REPO WARNING: {}", warning);
+        }
+        Ok(repo.to_graph())
+    }
+}
+
+/// Cargo.toml из удалённого репозитория (или по локальному пути)
+pub struct RemoteManifestSource;
+
+impl DependencySource for RemoteManifestSource {
+    fn description(&self) -> &'static str {
+        "NORMAL mode (Cargo.toml)"
+    }
+
+    fn is_full_graph(&self) -> bool {
+        false
+    }
+
+    fn load(&self, cfg: &AppConfig) -> Result<DependencyGraph, SourceError> {
+        let deps = cargo_parser::get_dependencies(&cfg.repo_source)?;
+        Ok(direct_graph(&cfg.package_name, deps))
+    }
+}
+
+/// Локальный Cargo.toml
+pub struct LocalManifestSource;
+
+impl DependencySource for LocalManifestSource {
+    fn description(&self) -> &'static str {
+        "LOCAL mode (local Cargo.toml)"
+    }
+
+    fn is_full_graph(&self) -> bool {
+        false
+    }
+
+    fn load(&self, cfg: &AppConfig) -> Result<DependencyGraph, SourceError> {
+        let content = cargo_parser::read_local_manifest(&cfg.repo_source)?;
+        let deps = cargo_parser::parse_manifest_dependencies(&content)?;
+        Ok(direct_graph(&cfg.package_name, deps))
+    }
+}
+
+/// Cargo.lock: полный граф с версиями пакетов
+pub struct LockfileSource;
+
+impl DependencySource for LockfileSource {
+    fn description(&self) -> &'static str {
+        "LOCKFILE mode (Cargo.lock)"
+    }
+
+    fn is_full_graph(&self) -> bool {
+        true
+    }
+
+    fn load(&self, cfg: &AppConfig) -> Result<DependencyGraph, SourceError> {
+        let packages = lockfile::load_lockfile(&cfg.repo_source)?;
+        let names = lockfile::node_names(&packages);
+
+        let mut graph = DependencyGraph::new();
+        for pkg in &packages {
+            let node = &names[&format!("{} {}", pkg.name, pkg.version)];
+            graph.set_version(node, &pkg.version);
+            if let Some(source) = &pkg.source {
+                graph.set_attribute(node, "source", source);
+            }
+            for reference in &pkg.dependencies {
+                if let Some(dep) = lockfile::resolve_reference(&names, reference) {
+                    graph.add_edge(node, dep);
+                }
+            }
+        }
+        Ok(graph)
+    }
+}
+
+// Граф вида "корень -> каждая прямая зависимость"
+fn direct_graph(root: &str, deps: Vec<ManifestDependency>) -> DependencyGraph {
+    let mut graph = DependencyGraph::new();
+    graph.ensure_node(root);
+    for d in deps {
+        graph.add_edge_with(root, &d.name, d.info);
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_resolve_from_registry() {
+        for (name, _) in SOURCES {
+            let mode: Mode = format!(" {name} ").parse().unwrap();
+            assert_eq!(mode.as_str(), name);
+        }
+        let mode: Mode = "lockfile".parse().unwrap();
+        assert!(source_for(mode).is_full_graph());
+        assert_eq!(
+            "svn".parse::<Mode>().unwrap_err(),
+            "unknown mode 'svn', expected one of 'test', 'real', 'local', 'lockfile'"
+        );
+    }
+}
@@ -0,0 +1,330 @@
+// Модуль для вычисления метрик графа: степени, транзитивный вес, глубина и центральность
+
+use std::collections::{HashSet, VecDeque};
+use std::str::FromStr;
+
+use crate::graph::DependencyGraph;
+use crate::json::JsonValue;
+use crate::scc::IndexedGraph;
+
+/// Метрики отдельного узла
+#[derive(Debug, Clone)]
+pub struct NodeStats {
+    pub name: String,
+    /// Число прямых зависимостей
+    pub deps: usize,
+    /// Число транзитивных зависимостей
+    pub transitive_deps: usize,
+    /// Число пакетов, напрямую зависящих от узла
+    pub rdeps: usize,
+    /// Число пакетов, транзитивно зависящих от узла
+    pub transitive_rdeps: usize,
+    /// Кратчайшее расстояние от корня (None - недостижим)
+    pub depth: Option<usize>,
+    /// Центральность по посредничеству (betweenness)
+    pub betweenness: f64,
+}
+
+/// Вес прямой зависимости корня
+#[derive(Debug, Clone)]
+pub struct DirectWeight {
+    pub name: String,
+    /// Сколько пакетов приносит зависимость (включая её саму)
+    pub transitive: usize,
+    /// Сколько из них не приносит больше никто из прямых зависимостей
+    pub unique: usize,
+}
+
+/// Метрики графа
+#[derive(Debug)]
+pub struct GraphStats {
+    pub nodes: Vec<NodeStats>,
+    pub direct: Vec<DirectWeight>,
+}
+
+/// Столбец для сортировки таблицы
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Deps,
+    TransitiveDeps,
+    Rdeps,
+    TransitiveRdeps,
+    Depth,
+    Betweenness,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "name" => Ok(SortKey::Name),
+            "deps" => Ok(SortKey::Deps),
+            "tdeps" => Ok(SortKey::TransitiveDeps),
+            "rdeps" => Ok(SortKey::Rdeps),
+            "trdeps" => Ok(SortKey::TransitiveRdeps),
+            "depth" => Ok(SortKey::Depth),
+            "betweenness" => Ok(SortKey::Betweenness),
+            other => Err(format!(
+                "unknown sort column '{other}', expected name, deps, tdeps, rdeps, trdeps, depth or betweenness"
+            )),
+        }
+    }
+}
+
+/// Вычисление метрик для всех узлов графа
+pub fn compute(graph: &DependencyGraph, root: &str) -> GraphStats {
+    let g = IndexedGraph::new(graph);
+    let n = g.names.len();
+    let mut radj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (v, deps) in g.adj.iter().enumerate() {
+        for &w in deps {
+            radj[w].push(v);
+        }
+    }
+
+    let root_idx = g.names.iter().position(|&name| name == root);
+    let depth = match root_idx {
+        Some(r) => bfs_distances(&g.adj, r),
+        None => vec![None; n],
+    };
+    let betweenness = betweenness(&g.adj);
+
+    let nodes = (0..n)
+        .map(|v| NodeStats {
+            name: g.names[v].to_string(),
+            deps: g.adj[v].len(),
+            transitive_deps: reachable(&g.adj, &[v]).len() - 1,
+            rdeps: radj[v].len(),
+            transitive_rdeps: reachable(&radj, &[v]).len() - 1,
+            depth: depth[v],
+            betweenness: betweenness[v],
+        })
+        .collect();
+
+    // Уникальный вес прямой зависимости: пакеты, недостижимые через остальные прямые зависимости
+    let mut direct = Vec::new();
+    if let Some(r) = root_idx {
+        for &d in &g.adj[r] {
+            let own = reachable(&g.adj, &[d]);
+            let others: Vec<usize> = g.adj[r].iter().copied().filter(|&o| o != d).collect();
+            let shared = reachable(&g.adj, &others);
+            let unique = own.iter().filter(|v| !shared.contains(v) && **v != r).count();
+            direct.push(DirectWeight { name: g.names[d].to_string(), transitive: own.len(), unique });
+        }
+        direct.sort_by(|a, b| b.unique.cmp(&a.unique).then_with(|| a.name.cmp(&b.name)));
+    }
+
+    GraphStats { nodes, direct }
+}
+
+// Множество узлов, достижимых из стартовых (включая их самих)
+fn reachable(adj: &[Vec<usize>], start: &[usize]) -> HashSet<usize> {
+    let mut seen: HashSet<usize> = start.iter().copied().collect();
+    let mut stack: Vec<usize> = start.to_vec();
+    while let Some(v) = stack.pop() {
+        for &w in &adj[v] {
+            if seen.insert(w) {
+                stack.push(w);
+            }
+        }
+    }
+    seen
+}
+
+// Кратчайшие расстояния от узла в рёбрах
+fn bfs_distances(adj: &[Vec<usize>], start: usize) -> Vec<Option<usize>> {
+    let mut dist = vec![None; adj.len()];
+    dist[start] = Some(0);
+    let mut queue = VecDeque::from([start]);
+    while let Some(v) = queue.pop_front() {
+        let d = dist[v].unwrap_or(0);
+        for &w in &adj[v] {
+            if dist[w].is_none() {
+                dist[w] = Some(d + 1);
+                queue.push_back(w);
+            }
+        }
+    }
+    dist
+}
+
+// Центральность по посредничеству (алгоритм Брандеса для невзвешенного ориентированного графа)
+fn betweenness(adj: &[Vec<usize>]) -> Vec<f64> {
+    let n = adj.len();
+    let mut cb = vec![0.0; n];
+
+    for s in 0..n {
+        let mut stack = Vec::new();
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut sigma = vec![0.0f64; n];
+        let mut dist: Vec<i64> = vec![-1; n];
+        sigma[s] = 1.0;
+        dist[s] = 0;
+        let mut queue = VecDeque::from([s]);
+
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            for &w in &adj[v] {
+                if dist[w] < 0 {
+                    dist[w] = dist[v] + 1;
+                    queue.push_back(w);
+                }
+                if dist[w] == dist[v] + 1 {
+                    sigma[w] += sigma[v];
+                    preds[w].push(v);
+                }
+            }
+        }
+
+        let mut delta = vec![0.0f64; n];
+        while let Some(w) = stack.pop() {
+            for &v in &preds[w] {
+                delta[v] += sigma[v] / sigma[w] * (1.0 + delta[w]);
+            }
+            if w != s {
+                cb[w] += delta[w];
+            }
+        }
+    }
+
+    cb
+}
+
+impl GraphStats {
+    /// Сортировка таблицы: имя - по возрастанию, числовые столбцы - по убыванию
+    pub fn sort_by(&mut self, key: SortKey) {
+        let by_name = |a: &NodeStats, b: &NodeStats| a.name.cmp(&b.name);
+        self.nodes.sort_by(|a, b| {
+            let ord = match key {
+                SortKey::Name => by_name(a, b),
+                SortKey::Deps => b.deps.cmp(&a.deps),
+                SortKey::TransitiveDeps => b.transitive_deps.cmp(&a.transitive_deps),
+                SortKey::Rdeps => b.rdeps.cmp(&a.rdeps),
+                SortKey::TransitiveRdeps => b.transitive_rdeps.cmp(&a.transitive_rdeps),
+                // Недостижимые узлы - в конце
+                SortKey::Depth => a.depth.unwrap_or(usize::MAX).cmp(&b.depth.unwrap_or(usize::MAX)),
+                SortKey::Betweenness => b.betweenness.total_cmp(&a.betweenness),
+            };
+            ord.then_with(|| by_name(a, b))
+        });
+    }
+
+    /// Текстовая таблица
+    pub fn to_text(&self) -> String {
+        let width = self.nodes.iter().map(|s| s.name.len()).max().unwrap_or(4).max(4);
+        let mut out = format!(
+            "{:<width$}  {:>5}  {:>6}  {:>6}  {:>7}  {:>5}  {:>11}\n",
+            "name", "deps", "tdeps", "rdeps", "trdeps", "depth", "betweenness"
+        );
+        for s in &self.nodes {
+            let depth = s.depth.map(|d| d.to_string()).unwrap_or_else(|| "-".into());
+            out.push_str(&format!(
+                "{:<width$}  {:>5}  {:>6}  {:>6}  {:>7}  {:>5}  {:>11.2}\n",
+                s.name, s.deps, s.transitive_deps, s.rdeps, s.transitive_rdeps, depth, s.betweenness
+            ));
+        }
+
+        if !self.direct.is_empty() {
+            out.push_str("\nDirect dependencies by unique transitive weight:\n");
+            let width = self.direct.iter().map(|d| d.name.len()).max().unwrap_or(4).max(4);
+            out.push_str(&format!("{:<width$}  {:>6}  {:>10}\n", "name", "unique", "transitive"));
+            for d in &self.direct {
+                out.push_str(&format!("{:<width$}  {:>6}  {:>10}\n", d.name, d.unique, d.transitive));
+            }
+        }
+        out
+    }
+
+    /// Представление в JSON
+    pub fn to_json(&self, root: &str) -> JsonValue {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|s| {
+                JsonValue::object(vec![
+                    ("name", s.name.as_str().into()),
+                    ("deps", s.deps.into()),
+                    ("transitive_deps", s.transitive_deps.into()),
+                    ("rdeps", s.rdeps.into()),
+                    ("transitive_rdeps", s.transitive_rdeps.into()),
+                    ("depth", s.depth.into()),
+                    ("betweenness", JsonValue::Number(s.betweenness)),
+                ])
+            })
+            .collect();
+        let direct = self
+            .direct
+            .iter()
+            .map(|d| {
+                JsonValue::object(vec![
+                    ("name", d.name.as_str().into()),
+                    ("unique", d.unique.into()),
+                    ("transitive", d.transitive.into()),
+                ])
+            })
+            .collect();
+
+        JsonValue::object(vec![
+            ("root", root.into()),
+            ("nodes", JsonValue::Array(nodes)),
+            ("direct", JsonValue::Array(direct)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // R -> A -> C -> D, R -> B -> C, R -> E -> F и отдельный пакет X
+    fn sample() -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in [("R", "A"), ("R", "B"), ("R", "E"), ("A", "C"), ("B", "C"), ("C", "D"), ("E", "F")] {
+            g.add_edge(from, to);
+        }
+        g.ensure_node("X");
+        g
+    }
+
+    #[test]
+    fn computes_hand_checked_metrics() {
+        let stats = compute(&sample(), "R");
+        let row = |name: &str| {
+            let s = stats.nodes.iter().find(|s| s.name == name).unwrap();
+            (s.deps, s.transitive_deps, s.rdeps, s.transitive_rdeps, s.depth)
+        };
+        assert_eq!(row("R"), (3, 6, 0, 0, Some(0)));
+        assert_eq!(row("C"), (1, 1, 2, 3, Some(2)));
+        assert_eq!(row("D"), (0, 0, 1, 4, Some(3)));
+        assert_eq!(row("X"), (0, 0, 0, 0, None));
+
+        // Кратчайшие пути R-C и R-D идут через A и B поровну, а A-D, B-D и R-D - через C
+        let betweenness: Vec<(&str, f64)> = stats.nodes.iter().map(|s| (s.name.as_str(), s.betweenness)).collect();
+        assert_eq!(
+            betweenness,
+            [("A", 1.0), ("B", 1.0), ("C", 3.0), ("D", 0.0), ("E", 1.0), ("F", 0.0), ("R", 0.0), ("X", 0.0)]
+        );
+    }
+
+    #[test]
+    fn counts_unique_weight_of_direct_dependencies() {
+        let stats = compute(&sample(), "R");
+        let direct: Vec<(&str, usize, usize)> =
+            stats.direct.iter().map(|d| (d.name.as_str(), d.unique, d.transitive)).collect();
+        // C и D приносят и A, и B, поэтому у них уникален только сам пакет
+        assert_eq!(direct, [("E", 2, 2), ("A", 1, 3), ("B", 1, 3)]);
+        assert!(compute(&sample(), "missing").direct.is_empty());
+    }
+
+    #[test]
+    fn sorts_numeric_columns_descending_with_name_ties() {
+        let mut stats = compute(&sample(), "R");
+        stats.sort_by(SortKey::TransitiveRdeps);
+        let names: Vec<&str> = stats.nodes.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["D", "C", "F", "A", "B", "E", "R", "X"]);
+        stats.sort_by(SortKey::Depth);
+        assert_eq!(stats.nodes.last().unwrap().name, "X");
+    }
+}
@@ -1,47 +1,940 @@
 // Модуль для загрузки тестового репозитория зависимостей из файла
+//
+// Формат строки: PKG[@VERSION] [{key=value, key="value", ...}]: DEP DEP ...
+// Зависимость:   [dev:|build:][?]NAME[@VERSION][(FEATURE, ...)]  (? - необязательная зависимость)
+// Имя пакета и значение атрибута в кавычках могут содержать любые символы (`\"` и `\\` внутри кавычек).
+// `#` вне кавычек начинает комментарий до конца строки, `\` в конце строки продолжает её на следующей,
+// пустые строки пропускаются. Зависимости разделяются пробелами или запятыми.
+// Старый формат `PKG: DEP DEP` остаётся допустимым.
+//
+// `include PATH` подключает другой файл (путь относительно подключающего файла, каждый файл
+// читается один раз). Пакет нельзя объявить повторно в другом файле; строка `extend PKG...: DEP...`
+// дополняет уже объявленный пакет зависимостями, атрибутами и версией. Пакет с именем include или
+// extend объявляется с именем в кавычках.
+//
+// Строгий режим: имена только из заглавных латинских букв, запятые запрещены,
+// повторные объявления пакетов и ссылки на необъявленные пакеты - ошибки, а не предупреждения.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
 
+use thiserror::Error;
+
+use crate::graph::{DependencyGraph, EdgeInfo, EdgeKind, NodeId};
 
 /// Возможные ошибки при работе с тестовым репозиторием
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum TestRepoError {
+    #[error("{0}")]
     ReadError(String),
+
+    #[error("invalid file format: {0}")]
     ParseError(String),
+
+    #[error("{0}")]
+    WriteError(String),
+
+    /// Неразрешимое или циклическое подключение файлов
+    #[error("invalid include: {0}")]
+    IncludeError(String),
+
+    /// Повторное объявление пакета в другом файле или противоречивое расширение
+    #[error("conflicting definitions: {0}")]
+    Conflict(String),
+
+    /// Ошибки проверки в строгом режиме (каждая с номером строки)
+    #[error("{} problem(s) in strict mode:\n  {}", .0.len(), .0.join("\n  "))]
+    Invalid(Vec<String>),
+}
+
+/// Содержимое файла тестового репозитория
+#[derive(Debug, Default)]
+pub struct TestRepo {
+    /// Пакеты в порядке объявления
+    pub packages: Vec<TestPackage>,
+    /// Прочитанные файлы в порядке подключения (первый - корневой)
+    pub files: Vec<String>,
+    /// Замечания проверки (повторы, ссылки на необъявленные пакеты) вне строгого режима
+    pub warnings: Vec<String>,
+}
+
+/// Строка объявления пакета
+#[derive(Debug, Default)]
+pub struct TestPackage {
+    pub name: String,
+    pub version: Option<String>,
+    pub attributes: BTreeMap<String, String>,
+    pub dependencies: Vec<TestDependency>,
+    /// Номер строки, с которой начинается объявление
+    pub line: usize,
+    /// Номер файла в `TestRepo::files`
+    pub file: usize,
+}
+
+/// Зависимость пакета
+#[derive(Debug)]
+pub struct TestDependency {
+    pub name: String,
+    /// Версия, указанная через `@` (используется, если сам пакет объявлен без версии)
+    pub version: Option<String>,
+    pub kind: EdgeKind,
+    pub optional: bool,
+    pub features: Vec<String>,
+}
+
+impl TestDependency {
+    // Сведения о ребре; у обычной обязательной зависимости их нет, как в старом формате
+    fn edge_info(&self) -> Option<EdgeInfo> {
+        (self.kind != EdgeKind::Normal || self.optional || !self.features.is_empty()).then(|| EdgeInfo {
+            kind: self.kind,
+            optional: self.optional,
+            features: self.features.clone(),
+            ..EdgeInfo::default()
+        })
+    }
+}
+
+impl TestRepo {
+    /// Построение графа: пакеты и рёбра в порядке объявления
+    pub fn to_graph(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        for pkg in &self.packages {
+            graph.ensure_node(&pkg.name);
+            if let Some(v) = &pkg.version {
+                graph.set_version(&pkg.name, v);
+            }
+            for (key, value) in &pkg.attributes {
+                graph.set_attribute(&pkg.name, key, value);
+            }
+            for dep in &pkg.dependencies {
+                match dep.edge_info() {
+                    Some(info) => graph.add_edge_with(&pkg.name, &dep.name, info),
+                    None => graph.add_edge(&pkg.name, &dep.name),
+                }
+            }
+        }
+        // Версии из ссылок на зависимости - только для пакетов, объявленных без версии
+        for pkg in &self.packages {
+            for dep in &pkg.dependencies {
+                if let Some(v) = &dep.version
+                    && graph.version(&dep.name).is_none()
+                {
+                    graph.set_version(&dep.name, v);
+                }
+            }
+        }
+        graph
+    }
+}
+
+/// Загрузка тестового репозитория из текстового файла вместе с подключёнными файлами.
+/// Пакеты возвращаются в порядке объявления; повторная строка пакета в том же файле заменяет
+/// предыдущую (вне строгого режима - с предупреждением).
+pub fn load_test_repo(path: &str, strict: bool) -> Result<TestRepo, TestRepoError> {
+    let mut loader = Loader { strict, ..Loader::default() };
+    loader.load(Path::new(path), None)?;
+    loader.finish()
+}
+
+// Состояние загрузки файлов с подключениями
+#[derive(Default)]
+struct Loader {
+    strict: bool,
+    repo: TestRepo,
+    position: HashMap<String, usize>,
+    /// Строки extend, применяемые после чтения всех файлов
+    extensions: Vec<TestPackage>,
+    /// Замечания с файлом и строкой для сортировки
+    problems: Vec<((usize, usize), String)>,
+    /// Цепочка подключений: канонический путь и номер файла
+    stack: Vec<(PathBuf, usize)>,
+    loaded: HashSet<PathBuf>,
+}
+
+impl Loader {
+    // Чтение файла; `from` - место директивы include, если файл подключён
+    fn load(&mut self, path: &Path, from: Option<&str>) -> Result<(), TestRepoError> {
+        let display = path.display().to_string();
+        let content = fs::read_to_string(path).map_err(|_| match from {
+            None => TestRepoError::ReadError(format!("Cannot read test repository file: {}", display)),
+            Some(at) => TestRepoError::IncludeError(format!("{}: cannot read included file {}", at, display)),
+        })?;
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(start) = self.stack.iter().position(|(p, _)| *p == canonical) {
+            let mut chain: Vec<&str> = self.stack[start..].iter().map(|&(_, f)| self.repo.files[f].as_str()).collect();
+            chain.push(&display);
+            return Err(TestRepoError::IncludeError(format!("include cycle: {}", chain.join(" -> "))));
+        }
+        // Файл, уже подключённый по другому пути включения, повторно не читается
+        if !self.loaded.insert(canonical.clone()) {
+            return Ok(());
+        }
+        let file = self.repo.files.len();
+        self.repo.files.push(display);
+        self.stack.push((canonical, file));
+
+        for (line_no, line) in logical_lines(&content) {
+            let at = self.location(file, line_no);
+            if let Some(target) = directive(&line, "include") {
+                let target = if target.starts_with('"') { unquote(target) } else { Ok(target.to_string()) }
+                    .map_err(|msg| TestRepoError::ParseError(format!("{}: {}", at, msg)))?;
+                let next = path.parent().unwrap_or(Path::new("")).join(target);
+                self.load(&next, Some(&at))?;
+                continue;
+            }
+            let (extension, text) = match directive(&line, "extend") {
+                Some(rest) => (true, rest),
+                None => (false, line.as_str()),
+            };
+            let package =
+                parse_line(text, self.strict).map_err(|msg| TestRepoError::ParseError(format!("{}: {}", at, msg)))?;
+            let package = TestPackage { line: line_no, file, ..package };
+            if extension {
+                self.extensions.push(package);
+            } else {
+                self.declare(package)?;
+            }
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn declare(&mut self, package: TestPackage) -> Result<(), TestRepoError> {
+        match self.position.get(&package.name) {
+            Some(&i) if self.repo.packages[i].file != package.file => {
+                let earlier = &self.repo.packages[i];
+                Err(TestRepoError::Conflict(format!(
+                    "{}: package '{}' is already declared in {}; use 'extend {}: ...' to add to it",
+                    self.location(package.file, package.line),
+                    package.name,
+                    self.location(earlier.file, earlier.line),
+                    package.name
+                )))
+            }
+            Some(&i) => {
+                let msg = format!("package '{}' is already declared on line {}", package.name, self.repo.packages[i].line);
+                self.problems.push(((package.file, package.line), msg));
+                self.repo.packages[i] = package;
+                Ok(())
+            }
+            None => {
+                self.position.insert(package.name.clone(), self.repo.packages.len());
+                self.repo.packages.push(package);
+                Ok(())
+            }
+        }
+    }
+
+    // Проверка ссылок, применение расширений и итоговые замечания
+    fn finish(mut self) -> Result<TestRepo, TestRepoError> {
+        // Ссылки на пакеты, у которых нет собственной строки
+        for pkg in self.repo.packages.iter().chain(&self.extensions) {
+            for dep in pkg.dependencies.iter().filter(|d| !self.position.contains_key(&d.name)) {
+                let msg = format!("'{}' depends on undeclared package '{}'", pkg.name, dep.name);
+                self.problems.push(((pkg.file, pkg.line), msg));
+            }
+        }
+
+        for ext in std::mem::take(&mut self.extensions) {
+            let at = self.location(ext.file, ext.line);
+            let Some(&i) = self.position.get(&ext.name) else {
+                return Err(TestRepoError::Conflict(format!("{}: 'extend' refers to undeclared package '{}'", at, ext.name)));
+            };
+            let base_at = self.location(self.repo.packages[i].file, self.repo.packages[i].line);
+            let base = &mut self.repo.packages[i];
+            let conflict = |what: String| TestRepoError::Conflict(format!("{}: {} declared in {}", at, what, base_at));
+            match (&base.version, ext.version) {
+                (Some(old), Some(new)) if *old != new => {
+                    return Err(conflict(format!("version {} of '{}' conflicts with version {}", new, ext.name, old)));
+                }
+                (None, Some(new)) => base.version = Some(new),
+                _ => {}
+            }
+            for (key, value) in ext.attributes {
+                match base.attributes.get(&key) {
+                    Some(old) if *old != value => {
+                        return Err(conflict(format!("attribute {}={} of '{}' conflicts with {}={}", key, value, ext.name, key, old)));
+                    }
+                    _ => base.attributes.insert(key, value),
+                };
+            }
+            base.dependencies.extend(ext.dependencies);
+        }
+
+        self.problems.sort_by_key(|&(at, _)| at);
+        let problems: Vec<String> =
+            self.problems.iter().map(|&((file, line), ref msg)| format!("{}: {}", self.location(file, line), msg)).collect();
+        if self.strict && !problems.is_empty() {
+            return Err(TestRepoError::Invalid(problems));
+        }
+        self.repo.warnings = problems;
+        Ok(self.repo)
+    }
+
+    // Место в файлах: строки корневого файла - просто номером, подключённых - с путём
+    fn location(&self, file: usize, line: usize) -> String {
+        match file {
+            0 => format!("line {}", line),
+            _ => format!("{}, line {}", self.repo.files[file], line),
+        }
+    }
+}
+
+/// Ключевые слова директив в начале строки
+const DIRECTIVES: [&str; 2] = ["include", "extend"];
+
+// Остаток строки после ключевого слова директивы (include, extend)
+fn directive<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    line.strip_prefix(keyword).filter(|rest| rest.starts_with(char::is_whitespace)).map(str::trim)
+}
+
+/// Запись графа в файл тестового репозитория; возвращает имя, под которым записан корень
+pub fn save(path: &str, graph: &DependencyGraph, root: &str, anonymize: bool) -> Result<String, TestRepoError> {
+    let (text, root) = to_text(graph, root, anonymize)?;
+    fs::write(path, text).map_err(|e| TestRepoError::WriteError(format!("cannot write {}: {}", path, e)))?;
+    Ok(root)
+}
+
+/// Текст тестового репозитория для графа: по строке на пакет (корень первым), зависимости
+/// в порядке обхода. При анонимизации пакеты получают имена A, B, C, ... (корень - A), а атрибуты,
+/// где бывают адреса источников, не записываются. Промежуточные узлы свёрнутых цепочек (via)
+/// в формате не представимы и опускаются
+pub fn to_text(graph: &DependencyGraph, root: &str, anonymize: bool) -> Result<(String, String), TestRepoError> {
+    let others = (0..graph.len()).map(NodeId::from_index).filter(|&id| graph.name(id) != root);
+    let order: Vec<NodeId> = graph.id(root).into_iter().chain(others).collect();
+    let name_of: HashMap<NodeId, String> = order
+        .iter()
+        .enumerate()
+        .map(|(i, &id)| (id, if anonymize { letter_name(i) } else { graph.name(id).to_string() }))
+        .collect();
+    let unwritable = |what: String| TestRepoError::WriteError(format!("{} cannot be written to a test repository", what));
+
+    let mut out = String::new();
+    if let Some(first) = order.first() {
+        out.push_str(&format!("# root: {}\n", name_of[first]));
+    }
+    for &id in &order {
+        let name = &name_of[&id];
+        out.push_str(&name_text(name));
+        if let Some(v) = graph.version(graph.name(id)) {
+            if !is_plain(v) {
+                return Err(unwritable(format!("version '{}' of '{}'", v, name)));
+            }
+            out.push('@');
+            out.push_str(v);
+        }
+        if let Some(attrs) = graph.attributes(graph.name(id)).filter(|a| !anonymize && !a.is_empty()) {
+            let mut pairs = Vec::new();
+            for (key, value) in attrs {
+                if !is_plain(key) || key.contains('=') {
+                    return Err(unwritable(format!("attribute key '{}' of '{}'", key, name)));
+                }
+                pairs.push(format!("{}={}", key, quote_if_needed(value)));
+            }
+            out.push_str(&format!(" {{{}}}", pairs.join(", ")));
+        }
+        out.push(':');
+        for &dep in graph.successors(id) {
+            out.push(' ');
+            if let Some(info) = graph.edge_info(graph.name(id), graph.name(dep)) {
+                if info.kind != EdgeKind::Normal {
+                    out.push_str(info.kind.as_str());
+                    out.push(':');
+                }
+                if info.optional {
+                    out.push('?');
+                }
+                out.push_str(&name_text(&name_of[&dep]));
+                if !info.features.is_empty() {
+                    if let Some(f) = info.features.iter().find(|f| !is_plain(f)) {
+                        return Err(unwritable(format!("feature '{}' of '{}'", f, name)));
+                    }
+                    out.push_str(&format!("({})", info.features.join(", ")));
+                }
+            } else {
+                out.push_str(&name_text(&name_of[&dep]));
+            }
+        }
+        out.push('\n');
+    }
+    let root = order.first().map(|id| name_of[id].clone()).unwrap_or_default();
+    Ok((out, root))
+}
+
+// Версии, фичи и ключи атрибутов записываются без кавычек, поэтому не должны содержать служебных
+// символов формата
+fn is_plain(s: &str) -> bool {
+    !s.is_empty() && !s.chars().any(|c| c.is_whitespace() || ":#{}(),@\"\\?".contains(c))
+}
+
+// Имя пакета; имена со служебными символами формата записываются в кавычках, как и имена
+// include и extend: без кавычек строка такого пакета с версией или атрибутами читается как директива
+fn name_text(name: &str) -> String {
+    match is_plain(name) && !DIRECTIVES.contains(&name) {
+        true => name.to_string(),
+        false => quote(name),
+    }
+}
+
+// Значение атрибута в кавычках, если без них оно прочиталось бы иначе
+fn quote_if_needed(value: &str) -> String {
+    if !value.starts_with('"') && value.trim() == value && !value.chars().any(|c| ",}:#\\".contains(c)) {
+        return value.to_string();
+    }
+    quote(value)
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Имя пакета по номеру: A, B, ..., Z, AA, AB, ... (допустимо и в строгом режиме)
+pub fn letter_name(index: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        n -= 1;
+        letters.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    letters.iter().rev().map(|&b| b as char).collect()
+}
+
+// Логические строки без комментариев, со склеенными продолжениями, и номера их первых строк
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (i, raw) in content.lines().enumerate() {
+        let text = find_unquoted(raw, '#').map_or(raw, |i| &raw[..i]).trim_end();
+        let (text, continues) = match text.strip_suffix('\\') {
+            Some(rest) => (rest, true),
+            None => (text, false),
+        };
+        let (_, joined) = current.get_or_insert_with(|| (i + 1, String::new()));
+        joined.push(' ');
+        joined.push_str(text);
+        if !continues {
+            let (start, joined) = current.take().unwrap_or_default();
+            if !joined.trim().is_empty() {
+                lines.push((start, joined.trim().to_string()));
+            }
+        }
+    }
+    // Продолжение в последней строке файла
+    if let Some((start, joined)) = current
+        && !joined.trim().is_empty()
+    {
+        lines.push((start, joined.trim().to_string()));
+    }
+    lines
+}
+
+// Разбор одной логической строки
+fn parse_line(line: &str, strict: bool) -> Result<TestPackage, String> {
+    // Разделение строки на имя пакета и список зависимостей
+    let Some(colon) = find_unquoted(line, ':') else {
+        return Err(format!("invalid line format: '{}'", line));
+    };
+    let (head, deps) = (&line[..colon], &line[colon + 1..]);
+
+    // Атрибуты в фигурных скобках после имени пакета
+    let (head, attributes) = match find_unquoted(head, '{').map(|i| (&head[..i], &head[i + 1..])) {
+        Some((head, attrs)) => {
+            let attrs = attrs.trim_end().strip_suffix('}').ok_or("missing '}' after attributes")?;
+            (head, parse_attributes(attrs)?)
+        }
+        None => (head, BTreeMap::new()),
+    };
+    let (name, version) = parse_name(head.trim(), strict)?;
+
+    let dependencies: Vec<TestDependency> = split_dependencies(deps, strict)?
+        .into_iter()
+        .map(|token| parse_dependency(token, strict))
+        .collect::<Result<_, _>>()?;
+
+    Ok(TestPackage {
+        name,
+        version: version.map(str::to_string),
+        attributes,
+        dependencies,
+        line: 0,
+        file: 0,
+    })
+}
+
+// Проверка имени пакета: в строгом режиме только заглавные латинские буквы, иначе любое непустое
+// имя (пробелы и служебные символы формата возможны только в кавычках)
+fn check_name(name: &str, strict: bool) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("missing package name".into());
+    }
+    if strict && !name.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(format!("invalid package name '{}': strict mode allows only capital Latin letters", name));
+    }
+    Ok(())
+}
+
+// Атрибуты вида key=value, разделённые запятыми
+fn parse_attributes(s: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut attributes = BTreeMap::new();
+    for pair in split_unquoted(s, ',').into_iter().map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').ok_or_else(|| format!("attribute '{}' must be key=value", pair))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("attribute '{}' has an empty key", pair));
+        }
+        let value = value.trim();
+        let value = if value.starts_with('"') { unquote(value)? } else { value.to_string() };
+        attributes.insert(key.to_string(), value);
+    }
+    Ok(attributes)
+}
+
+// Значение в кавычках с экранированием `\"` и `\\`
+fn unquote(s: &str) -> Result<String, String> {
+    let inner = s
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| format!("unterminated quoted value {}", s))?;
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next().ok_or_else(|| format!("unterminated quoted value {}", s))?),
+            '"' => return Err(format!("unexpected '\"' in quoted value {}", s)),
+            c => value.push(c),
+        }
+    }
+    Ok(value)
+}
+
+// Позиция первого символа `target` вне кавычек
+fn find_unquoted(s: &str, target: char) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == target {
+            return Some(i);
+        }
+    }
+    None
 }
 
-/// Загрузка тестового репозитория из текстового файла.
-pub fn load_test_repo(path: &str) -> Result<HashMap<String, Vec<String>>, TestRepoError> {
-    // Считывание файла в строку
-    let content = fs::read_to_string(path)
-        .map_err(|_| TestRepoError::ReadError(format!("Cannot read test repository file: {}", path)))?;
+// Разбиение по разделителю вне кавычек
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(i) = find_unquoted(rest, sep) {
+        parts.push(&rest[..i]);
+        rest = &rest[i + sep.len_utf8()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+// Зависимости разделяются пробелами или запятыми (в строгом режиме - только пробелами);
+// внутри скобок со списком фич запятые и пробелы разделяют фичи
+fn split_dependencies(deps: &str, strict: bool) -> Result<Vec<&str>, String> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let (mut quoted, mut escaped) = (false, false);
+    for (i, c) in deps.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 && strict => {
+                return Err("commas are not allowed in strict mode, separate dependencies with spaces".into());
+            }
+            c if depth == 0 && (c == ',' || c.is_whitespace()) => {
+                if start < i {
+                    tokens.push(&deps[start..i]);
+                }
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    if start < deps.len() {
+        tokens.push(&deps[start..]);
+    }
+    Ok(tokens)
+}
+
+// Зависимость: [dev:|build:][?]NAME[@VERSION][(FEATURE, ...)]
+fn parse_dependency(token: &str, strict: bool) -> Result<TestDependency, String> {
+    let (kind, rest) = match find_unquoted(token, ':').map(|i| (&token[..i], &token[i + 1..])) {
+        Some((kind, rest)) => match kind {
+            "dev" => (EdgeKind::Dev, rest),
+            "build" => (EdgeKind::Build, rest),
+            other => return Err(format!("unknown dependency kind '{}' in '{}'", other, token)),
+        },
+        None => (EdgeKind::Normal, token),
+    };
+    let (optional, rest) = match rest.strip_prefix('?') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let (rest, features) = match find_unquoted(rest, '(').map(|i| (&rest[..i], &rest[i + 1..])) {
+        Some((rest, list)) => {
+            let list = list.strip_suffix(')').ok_or_else(|| format!("missing ')' after features in '{}'", token))?;
+            let features = list.split([',', ' ']).filter(|f| !f.is_empty()).map(str::to_string).collect();
+            (rest, features)
+        }
+        None => (rest, Vec::new()),
+    };
+    let (name, version) = parse_name(rest, strict)?;
+    Ok(TestDependency { name, version: version.map(str::to_string), kind, optional, features })
+}
+
+// Имя пакета (возможно, в кавычках) и версия после `@`: NAME[@VERSION] или "NAME"[@VERSION]
+fn parse_name(s: &str, strict: bool) -> Result<(String, Option<&str>), String> {
+    let (name, version) = match find_unquoted(s, '@') {
+        Some(i) if i + 1 == s.len() => return Err(format!("empty version in '{}'", s)),
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let name = if name.starts_with('"') { unquote(name)? } else { name.to_string() };
+    check_name(&name, strict)?;
+    Ok((name, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Граф со всеми возможностями формата: версии, атрибуты, типы рёбер, необязательные
+    // зависимости, фичи, имена в кавычках, циклы и петля
+    fn sample() -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        let dev = EdgeInfo { kind: EdgeKind::Dev, ..EdgeInfo::default() };
+        let build_optional = EdgeInfo { kind: EdgeKind::Build, optional: true, ..EdgeInfo::default() };
+        let features = EdgeInfo { optional: true, features: vec!["derive".into(), "std".into()], ..EdgeInfo::default() };
+
+        g.set_version("app", "1.0.0");
+        g.set_attribute("app", "license", "MIT OR Apache-2.0");
+        g.set_attribute("app", "source", "registry+https://example.com/index, \"quoted\" {x}: #1 \\");
+        g.add_edge("app", "core");
+        g.add_edge_with("app", "serde@1.0.190", features);
+        g.add_edge_with("app", "test utils", dev);
+        g.add_edge_with("core", "{log, fmt}", build_optional);
+        g.add_edge("core", "gtk+");
+        g.add_edge("{log, fmt}", "core");
+        g.add_edge("gtk+", "gtk+");
+        g.set_version("serde@1.0.190", "1.0.190");
+        g.set_attribute("gtk+", "team", "ui");
+        g.ensure_node("org/unused");
+        g
+    }
+
+    // Ожидаемый граф после анонимизации: корень - A, остальные по порядку добавления, без атрибутов
+    fn anonymized(graph: &DependencyGraph, root: &str) -> DependencyGraph {
+        let mut order = vec![root];
+        order.extend((0..graph.len()).map(|i| graph.name(NodeId::from_index(i))).filter(|&n| n != root));
+        let letter = |name: &str| letter_name(order.iter().position(|&n| n == name).unwrap());
+        let mut out = DependencyGraph::new();
+        for &name in &order {
+            out.ensure_node(&letter(name));
+            if let Some(v) = graph.version(name) {
+                out.set_version(&letter(name), v);
+            }
+            for dep in graph.dependencies(name) {
+                let info = graph.edge_info(name, dep).cloned().unwrap_or_default();
+                out.add_edge_with(&letter(name), &letter(dep), info);
+            }
+        }
+        out
+    }
+
+    // Сохранение во временный файл и загрузка обратно
+    fn reload(graph: &DependencyGraph, root: &str, anonymize: bool, strict: bool) -> (DependencyGraph, String) {
+        let path = std::env::temp_dir().join(format!("test-repo-{}-{}-{}.txt", std::process::id(), root, anonymize));
+        let path = path.to_str().unwrap();
+        let saved_root = save(path, graph, root, anonymize).unwrap();
+        let repo = load_test_repo(path, strict);
+        let _ = fs::remove_file(path);
+        let repo = repo.unwrap();
+        assert!(repo.warnings.is_empty(), "{:?}", repo.warnings);
+        assert_eq!(repo.packages[0].name, saved_root);
+        (repo.to_graph(), saved_root)
+    }
+
+    #[test]
+    fn round_trips_graph() {
+        let graph = sample();
+        let (loaded, root) = reload(&graph, "app", false, false);
+        assert_eq!(root, "app");
+        assert_eq!(loaded.describe(), graph.describe());
+    }
+
+    #[test]
+    fn round_trips_anonymized_graph() {
+        let graph = sample();
+        // Анонимные имена допустимы и в строгом режиме
+        let (loaded, root) = reload(&graph, "core", true, true);
+        assert_eq!(root, "A");
+        assert_eq!(loaded.describe(), anonymized(&graph, "core").describe());
+        assert!(loaded.node_names().iter().all(|n| n.chars().all(|c| c.is_ascii_uppercase())));
+    }
+
+    #[test]
+    fn writes_root_first_and_quotes_names() {
+        let (text, _) = to_text(&sample(), "core", false).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "# root: core");
+        assert_eq!(lines[1], "core: build:?\"{log, fmt}\" gtk+");
+        assert!(lines.contains(&"\"serde@1.0.190\"@1.0.190:"));
+        assert!(lines.contains(&"\"test utils\":"));
+    }
+
+    #[test]
+    fn rejects_unwritable_versions() {
+        let mut graph = sample();
+        graph.set_version("core", "1.0 beta");
+        let err = to_text(&graph, "app", false).unwrap_err();
+        assert_eq!(err.to_string(), "version '1.0 beta' of 'core' cannot be written to a test repository");
+    }
+
+    // Краткая запись разобранной строки: name@version {key=value, ...}: kind:?dep@version(features)
+    fn summary(pkg: &TestPackage) -> String {
+        let mut out = pkg.name.clone();
+        if let Some(v) = &pkg.version {
+            out.push_str(&format!("@{v}"));
+        }
+        if !pkg.attributes.is_empty() {
+            let pairs: Vec<String> = pkg.attributes.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            out.push_str(&format!(" {{{}}}", pairs.join(", ")));
+        }
+        out.push(':');
+        for dep in &pkg.dependencies {
+            out.push(' ');
+            if dep.kind != EdgeKind::Normal {
+                out.push_str(&format!("{}:", dep.kind.as_str()));
+            }
+            if dep.optional {
+                out.push('?');
+            }
+            out.push_str(&dep.name);
+            if let Some(v) = &dep.version {
+                out.push_str(&format!("@{v}"));
+            }
+            if !dep.features.is_empty() {
+                out.push_str(&format!("({})", dep.features.join(",")));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn parses_lines() {
+        let cases = [
+            ("A: B C", "A: B C"),
+            ("A: B, C,D", "A: B C D"),
+            ("A:", "A:"),
+            ("gtk+: org/pkg lib-1_2", "gtk+: org/pkg lib-1_2"),
+            ("app@1.0.0: core@0.3", "app@1.0.0: core@0.3"),
+            ("app {license=MIT, team = core }: B", "app {license=MIT, team=core}: B"),
+            (r#"app {note="a, b: c} # \"q\" \\"}: B"#, r#"app {note=a, b: c} # "q" \}: B"#),
+            ("app: dev:tester build:cc ?log", "app: dev:tester build:cc ?log"),
+            ("app: build:?cc dev:?mock@2", "app: build:?cc dev:?mock@2"),
+            ("app: serde(derive, std) ?tokio@1(rt,macros)", "app: serde(derive,std) ?tokio@1(rt,macros)"),
+            (r#""test utils"@2 {k=v}: "a b"@1 "x:y"(f) dev:"c, d""#, "test utils@2 {k=v}: a b@1 x:y(f) dev:c, d"),
+        ];
+        for (line, expected) in cases {
+            let pkg = parse_line(line, false).unwrap_or_else(|e| panic!("{line}: {e}"));
+            assert_eq!(summary(&pkg), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let cases = [
+            ("A B C", "invalid line format: 'A B C'"),
+            (r#""A: B"#, r#"invalid line format: '"A: B'"#),
+            (": B", "missing package name"),
+            ("A@: B", "empty version in 'A@'"),
+            ("A {x=1: B", "missing '}' after attributes"),
+            ("A {x}: B", "attribute 'x' must be key=value"),
+            ("A {=1}: B", "attribute '=1' has an empty key"),
+            ("A: foo:B", "unknown dependency kind 'foo' in 'foo:B'"),
+            ("A: B(x", "missing ')' after features in 'B(x'"),
+            (r#"A {k="a" "b"}: C"#, r#"unexpected '"' in quoted value "a" "b""#),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse_line(line, false).unwrap_err(), expected, "{line}");
+        }
+    }
 
-    let mut map = HashMap::new();
+    #[test]
+    fn joins_continuations_and_strips_comments() {
+        let content = "# header\n\nA: B \\\n   C # comment\n\n\"x#y\" {k=\"#\"}: D  # tail\nE: \\\n";
+        let lines: Vec<(usize, String)> = logical_lines(content)
+            .into_iter()
+            .map(|(no, line)| (no, summary(&parse_line(&line, false).unwrap())))
+            .collect();
+        let expected = [(3, "A: B C"), (6, "x#y {k=#}: D"), (7, "E:")];
+        assert_eq!(lines, expected.map(|(no, s)| (no, s.to_string())));
+    }
 
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue; // пропускаем пустые строки
+    // Загрузка набора файлов из временного каталога; первый файл - корневой
+    fn load_files(tag: &str, files: &[(&str, &str)], strict: bool) -> (Result<TestRepo, TestRepoError>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("test-repo-{}-{}", std::process::id(), tag));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
         }
+        let result = load_test_repo(dir.join(files[0].0).to_str().unwrap(), strict);
+        let _ = fs::remove_dir_all(&dir);
+        (result, dir)
+    }
+
+    fn load_str(tag: &str, content: &str, strict: bool) -> Result<TestRepo, TestRepoError> {
+        load_files(tag, &[("repo.txt", content)], strict).0
+    }
+
+    #[test]
+    fn strict_mode_rejects_names_and_commas() {
+        let err = load_str("strict-name", "A: B\nB: c\n", true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid file format: line 2: invalid package name 'c': strict mode allows only capital Latin letters"
+        );
+        let err = load_str("strict-comma", "A: B, C\nB:\nC:\n", true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid file format: line 1: commas are not allowed in strict mode, separate dependencies with spaces"
+        );
+        // Вне строгого режима те же строки допустимы
+        let repo = load_str("lax-name", "A: b, C\nb:\nC:\n", false).unwrap();
+        assert!(repo.warnings.is_empty());
+        assert_eq!(repo.packages[0].dependencies.len(), 2);
+    }
+
+    #[test]
+    fn reports_duplicates_and_undeclared_packages() {
+        let content = "A: B \\\n  C\nB: D\n\nA: B\nC:\n";
+        let repo = load_str("warnings", content, false).unwrap();
+        assert_eq!(
+            repo.warnings,
+            ["line 3: 'B' depends on undeclared package 'D'", "line 5: package 'A' is already declared on line 1"]
+        );
+        // Повторная строка заменяет предыдущую, позиция пакета сохраняется
+        assert_eq!(repo.packages.iter().map(summary).collect::<Vec<_>>(), ["A: B", "B: D", "C:"]);
+
+        let err = load_str("strict-warnings", content, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "2 problem(s) in strict mode:\n  line 3: 'B' depends on undeclared package 'D'\n  \
+             line 5: package 'A' is already declared on line 1"
+        );
+    }
 
-        // Разделение строки на имя пакета и список зависимостей
-        if let Some((pkg, deps)) = trimmed.split_once(':') {
-            let pkg = pkg.trim();
-            let deps: Vec<String> = deps
-                .split_whitespace()
-                .map(|d| d.trim().to_string())
-                .filter(|d| !d.is_empty())
-                .collect();
+    #[test]
+    fn reports_parse_errors_with_line_numbers() {
+        let err = load_str("parse-error", "# comment\nA: B\n\nB: \\\n  dev:?C(x\n", false).unwrap_err();
+        assert_eq!(err.to_string(), "invalid file format: line 4: missing ')' after features in 'dev:?C(x'");
+        let err = load_test_repo("no/such/repo.txt", false).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot read test repository file: no/such/repo.txt");
+    }
+
+    #[test]
+    fn reports_include_cycle_chain() {
+        let files =
+            [("a.txt", "A: B\ninclude b.txt\n"), ("b.txt", "B:\ninclude \"c.txt\"\n"), ("c.txt", "include a.txt\n")];
+        let (result, dir) = load_files("include-cycle", &files, false);
+        let path = |name: &str| dir.join(name).display().to_string();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "invalid include: include cycle: {} -> {} -> {} -> {}",
+                path("a.txt"),
+                path("b.txt"),
+                path("c.txt"),
+                path("a.txt")
+            )
+        );
+    }
+
+    #[test]
+    fn loads_diamond_include_once() {
+        let files = [
+            ("root.txt", "include left.txt\ninclude right.txt\nA: L R\n"),
+            ("left.txt", "include common.txt\nL: C\n"),
+            ("right.txt", "include common.txt\nR: C\n"),
+            ("common.txt", "C:\n"),
+        ];
+        let (result, dir) = load_files("include-diamond", &files, true);
+        let repo = result.unwrap();
+        let names = ["root.txt", "left.txt", "common.txt", "right.txt"];
+        assert_eq!(repo.files, names.map(|n| dir.join(n).display().to_string()));
+        assert_eq!(repo.packages.iter().map(summary).collect::<Vec<_>>(), ["C:", "L: C", "R: C", "A: L R"]);
+    }
 
-            map.insert(pkg.to_string(), deps);
-        } else {
-            return Err(TestRepoError::ParseError(format!(
-                "Invalid line format: '{}'",
-                trimmed
-            )));
+    #[test]
+    fn rejects_redeclaration_in_included_file() {
+        let files = [("root.txt", "A: B\ninclude other.txt\n"), ("other.txt", "B:\nA: B\n")];
+        let (result, dir) = load_files("include-redeclare", &files, false);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "conflicting definitions: {}, line 2: package 'A' is already declared in line 1; use 'extend A: ...' to add to it",
+                dir.join("other.txt").display()
+            )
+        );
+    }
+
+    #[test]
+    fn extends_declared_packages() {
+        let content = "A {team=core}: B\nB:\nextend A@1.0 {team=core, os=linux}: dev:C\nC:\n";
+        let repo = load_str("extend", content, true).unwrap();
+        let packages: Vec<String> = repo.packages.iter().map(summary).collect();
+        assert_eq!(packages, ["A@1.0 {os=linux, team=core}: B dev:C", "B:", "C:"]);
+    }
+
+    #[test]
+    fn rejects_conflicting_extensions() {
+        let cases = [
+            ("A@1: B\nB:\nextend A@2: B\n", "line 3: version 2 of 'A' conflicts with version 1 declared in line 1"),
+            ("A {k=x}: B\nB:\nextend A {k=y}:\n", "line 3: attribute k=y of 'A' conflicts with k=x declared in line 1"),
+            ("A: B\nB:\nextend X: A\n", "line 3: 'extend' refers to undeclared package 'X'"),
+        ];
+        for (i, (content, expected)) in cases.into_iter().enumerate() {
+            let err = load_str(&format!("extend-conflict-{i}"), content, false).unwrap_err();
+            assert_eq!(err.to_string(), format!("conflicting definitions: {expected}"));
         }
     }
 
-    Ok(map)
+    #[test]
+    fn round_trips_directive_names() {
+        let mut graph = DependencyGraph::new();
+        graph.set_version("include", "1.0");
+        graph.set_attribute("extend", "team", "core");
+        graph.add_edge("include", "extend");
+        graph.add_edge("extend", "include");
+        let (text, _) = to_text(&graph, "include", false).unwrap();
+        assert_eq!(text, "# root: include\n\"include\"@1.0: \"extend\"\n\"extend\" {team=core}: \"include\"\n");
+        let (loaded, _) = reload(&graph, "include", false, false);
+        assert_eq!(loaded.describe(), graph.describe());
+    }
 }
@@ -0,0 +1,164 @@
+// Модуль с преобразованиями графа перед выводом: конденсация, транзитивная редукция,
+// сворачивание линейных цепочек
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::graph::DependencyGraph;
+use crate::scc::{self, IndexedGraph};
+
+/// Преобразование графа
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Свернуть каждую компоненту сильной связности в один узел
+    Condense,
+    /// Удалить рёбра, следующие из более длинных путей
+    Reduce,
+    /// Свернуть цепочки узлов с одним родителем и одним потомком в подписанное ребро
+    CollapseChains,
+}
+
+impl Transform {
+    /// Порядок применения преобразований
+    const ORDER: [Transform; 3] = [Transform::Condense, Transform::Reduce, Transform::CollapseChains];
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "condense" => Ok(Transform::Condense),
+            "reduce" => Ok(Transform::Reduce),
+            "collapse-chains" => Ok(Transform::CollapseChains),
+            other => Err(format!(
+                "unknown transform '{other}', expected 'condense', 'reduce' or 'collapse-chains'"
+            )),
+        }
+    }
+}
+
+/// Разбор списка преобразований, разделённых запятыми или пробелами
+pub fn parse_list(s: &str) -> Result<Vec<Transform>, String> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Применение преобразований в фиксированном порядке (condense, reduce, collapse-chains).
+/// Возвращает новый граф и имя корня в нём (при конденсации корень становится узлом компоненты).
+pub fn apply(graph: &DependencyGraph, root: &str, transforms: &[Transform]) -> (DependencyGraph, String) {
+    let mut graph = graph.subgraph(|_| true);
+    let mut root = root.to_string();
+
+    for t in Transform::ORDER {
+        if !transforms.contains(&t) {
+            continue;
+        }
+        graph = match t {
+            Transform::Condense => {
+                let condensation = scc::condense(&graph);
+                if let Some(r) = condensation.component_of.get(&root) {
+                    root = r.clone();
+                }
+                condensation.graph
+            }
+            Transform::Reduce => transitive_reduction(&graph),
+            Transform::CollapseChains => collapse_chains(&graph, &root),
+        };
+    }
+    (graph, root)
+}
+
+/// Транзитивная редукция. Рёбра между компонентами сильной связности удаляются,
+/// если компонента-цель достижима другим путём; рёбра внутри компонент сохраняются.
+pub fn transitive_reduction(graph: &DependencyGraph) -> DependencyGraph {
+    let condensation = scc::condense(graph);
+    let g = IndexedGraph::new(&condensation.graph);
+
+    // Ребро v -> w избыточно, если w достижима из другого прямого потомка v
+    let mut redundant: HashSet<(&str, &str)> = HashSet::new();
+    for v in 0..g.names.len() {
+        for &w in &g.adj[v] {
+            let others: Vec<usize> = g.adj[v].iter().copied().filter(|&o| o != w).collect();
+            if reaches(&g.adj, &others, w) {
+                redundant.insert((g.names[v], g.names[w]));
+            }
+        }
+    }
+
+    let mut out = graph.subgraph(|_| true);
+    for name in graph.node_names() {
+        for dep in graph.dependencies(name) {
+            let (from, to) = (&condensation.component_of[name], &condensation.component_of[dep]);
+            if from != to && redundant.contains(&(from.as_str(), to.as_str())) {
+                out.remove_edge(name, dep);
+            }
+        }
+    }
+    out
+}
+
+// Достижим ли `target` из какого-либо стартового узла
+fn reaches(adj: &[Vec<usize>], start: &[usize], target: usize) -> bool {
+    let mut seen: HashSet<usize> = start.iter().copied().collect();
+    let mut stack = start.to_vec();
+    while let Some(v) = stack.pop() {
+        if v == target {
+            return true;
+        }
+        for &w in &adj[v] {
+            if seen.insert(w) {
+                stack.push(w);
+            }
+        }
+    }
+    false
+}
+
+/// Сворачивание линейных цепочек: узлы (кроме корня) с одним родителем и одним потомком
+/// удаляются, а родитель соединяется с потомком ребром с подписью "via ..."
+pub fn collapse_chains(graph: &DependencyGraph, root: &str) -> DependencyGraph {
+    let chain: HashSet<&str> = graph
+        .node_names()
+        .into_iter()
+        .filter(|&name| {
+            name != root
+                && graph.dependents(name).count() == 1
+                && graph.dependents(name).all(|p| p != name)
+                && graph.dependencies(name).count() == 1
+                && graph.dependencies(name).all(|d| d != name)
+        })
+        .collect();
+
+    // Проход по цепочкам от каждого узла, который сам не сворачивается. У звена цепочки
+    // единственный родитель, поэтому проход всегда заканчивается на обычном узле
+    let mut collapsed: HashSet<&str> = HashSet::new();
+    let mut shortcuts: Vec<(&str, &str, Vec<&str>)> = Vec::new();
+    for name in graph.node_names().into_iter().filter(|n| !chain.contains(n)) {
+        let mut targets: HashSet<&str> = graph.dependencies(name).collect();
+        for dep in graph.dependencies(name) {
+            let mut via = Vec::new();
+            let mut end = dep;
+            while chain.contains(end) {
+                via.push(end);
+                end = graph.dependencies(end).next().unwrap_or(end);
+            }
+            // Цепочка не сворачивается в петлю и в ребро, которое уже есть у родителя
+            if !via.is_empty() && end != name && targets.insert(end) {
+                collapsed.extend(via.iter().copied());
+                shortcuts.push((name, end, via));
+            }
+        }
+    }
+
+    // Узлы колец из одних звеньев недостижимы из обычных узлов и остаются как есть
+    let mut out = graph.subgraph(|name| !collapsed.contains(name));
+    for (from, to, via) in shortcuts {
+        let mut info = graph.edge_info(from, via[0]).cloned().unwrap_or_default();
+        info.via = via.iter().map(|v| v.to_string()).collect();
+        out.add_edge_with(from, to, info);
+    }
+    out
+}
@@ -0,0 +1,378 @@
+// Модуль для объяснения, почему пакет присутствует в графе: пути от корня до пакета
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::graph::{DependencyGraph, Direction, Limits};
+use crate::json::JsonValue;
+
+/// Пути от корня до целевого пакета
+#[derive(Debug)]
+pub struct WhyReport {
+    pub root: String,
+    pub target: String,
+    /// Простые пути root -> ... -> target
+    pub paths: Vec<Vec<String>>,
+    /// Перечисление остановлено по лимиту количества путей
+    pub truncated: bool,
+}
+
+/// Поиск путей от `root` до `target`: все простые пути (не более `max_paths`)
+/// или `k` кратчайших; `max_len` ограничивает длину пути в рёбрах
+pub fn explain(
+    graph: &DependencyGraph,
+    root: &str,
+    target: &str,
+    k: Option<usize>,
+    max_len: Option<usize>,
+    max_paths: usize,
+) -> WhyReport {
+    // Обход ограничивается предками цели: из остальных узлов цель недостижима
+    let (ancestors, _) = graph.restrict(target, "", Direction::Reverse, Limits::default());
+    let allowed: HashSet<&str> = ancestors.node_names().into_iter().collect();
+
+    let mut report = WhyReport {
+        root: root.to_string(),
+        target: target.to_string(),
+        paths: Vec::new(),
+        truncated: false,
+    };
+    if !allowed.contains(root) {
+        return report;
+    }
+
+    match k {
+        Some(k) => report.paths = shortest_paths(graph, root, target, &allowed, k, max_len),
+        None => {
+            report.truncated = !all_paths(graph, root, target, &allowed, max_len, max_paths, &mut report.paths);
+            report.paths.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        }
+    }
+    report
+}
+
+// Поиск в глубину всех простых путей до цели с явным стеком, чтобы длинные цепочки
+// не переполняли стек вызовов; false, если достигнут лимит количества
+fn all_paths(
+    graph: &DependencyGraph,
+    root: &str,
+    target: &str,
+    allowed: &HashSet<&str>,
+    max_len: Option<usize>,
+    max_paths: usize,
+    found: &mut Vec<Vec<String>>,
+) -> bool {
+    if root == target {
+        found.push(vec![root.to_string()]);
+        return true;
+    }
+    // Ещё не просмотренные зависимости узла в обратном порядке (pop выдаёт порядок объявления);
+    // узлы на пределе длины не раскрываются
+    let pending = |name: &str, path_len: usize| -> Vec<String> {
+        if max_len.is_some_and(|m| path_len > m) {
+            return Vec::new();
+        }
+        let mut deps: Vec<String> =
+            graph.dependencies(name).filter(|d| allowed.contains(d)).map(str::to_string).collect();
+        deps.reverse();
+        deps
+    };
+
+    let mut path = vec![root.to_string()];
+    let mut on_path = HashSet::from([root.to_string()]);
+    let mut stack = vec![pending(root, 1)];
+    while let Some(frame) = stack.last_mut() {
+        let Some(dep) = frame.pop() else {
+            stack.pop();
+            if let Some(name) = path.pop() {
+                on_path.remove(&name);
+            }
+            continue;
+        };
+        if on_path.contains(&dep) {
+            continue;
+        }
+        if dep == target {
+            if found.len() >= max_paths {
+                return false;
+            }
+            let mut complete = path.clone();
+            complete.push(dep);
+            found.push(complete);
+            continue;
+        }
+        let deps = pending(&dep, path.len() + 1);
+        on_path.insert(dep.clone());
+        path.push(dep);
+        stack.push(deps);
+    }
+    true
+}
+
+// k кратчайших простых путей по алгоритму Йена: каждый следующий путь - кратчайшее
+// ответвление от уже найденных, отдельные ответвления ищутся обходом в ширину.
+// Пути идут по неубыванию длины
+fn shortest_paths(
+    graph: &DependencyGraph,
+    root: &str,
+    target: &str,
+    allowed: &HashSet<&str>,
+    k: usize,
+    max_len: Option<usize>,
+) -> Vec<Vec<String>> {
+    let mut found: Vec<Vec<String>> = Vec::new();
+    if k == 0 {
+        return found;
+    }
+    let no_nodes = HashSet::new();
+    let no_edges = HashSet::new();
+    match bfs_path(graph, root, target, allowed, &no_nodes, &no_edges, max_len) {
+        Some(first) => found.push(first),
+        None => return found,
+    }
+
+    let mut candidates: BTreeSet<(usize, Vec<String>)> = BTreeSet::new();
+    while found.len() < k {
+        let previous = found.last().unwrap().clone();
+        for i in 0..previous.len() - 1 {
+            let spur = previous[i].as_str();
+            let prefix = &previous[..=i];
+            // Рёбра, по которым найденные пути с тем же началом уходят от узла ответвления
+            let removed_edges: HashSet<(&str, &str)> = found
+                .iter()
+                .filter(|p| p.len() > i + 1 && &p[..=i] == prefix)
+                .map(|p| (p[i].as_str(), p[i + 1].as_str()))
+                .collect();
+            // Узлы начала пути, кроме узла ответвления: путь должен остаться простым
+            let removed_nodes: HashSet<&str> = previous[..i].iter().map(String::as_str).collect();
+            let remaining = max_len.map(|m| m.saturating_sub(i));
+            if let Some(tail) = bfs_path(graph, spur, target, allowed, &removed_nodes, &removed_edges, remaining) {
+                let mut candidate = prefix[..i].to_vec();
+                candidate.extend(tail);
+                if !found.contains(&candidate) {
+                    candidates.insert((candidate.len(), candidate));
+                }
+            }
+        }
+        match candidates.pop_first() {
+            Some((_, path)) => found.push(path),
+            None => break,
+        }
+    }
+    found
+}
+
+// Кратчайший путь обходом в ширину без удалённых узлов и рёбер, не длиннее `max_len` рёбер;
+// зависимости просматриваются по имени, поэтому из равных по длине выбирается наименьший
+fn bfs_path(
+    graph: &DependencyGraph,
+    from: &str,
+    target: &str,
+    allowed: &HashSet<&str>,
+    removed_nodes: &HashSet<&str>,
+    removed_edges: &HashSet<(&str, &str)>,
+    max_len: Option<usize>,
+) -> Option<Vec<String>> {
+    let mut parent: HashMap<String, (Option<String>, usize)> = HashMap::from([(from.to_string(), (None, 0))]);
+    let mut queue = VecDeque::from([from.to_string()]);
+    while let Some(name) = queue.pop_front() {
+        if name == target {
+            let mut path = vec![name];
+            while let Some((Some(prev), _)) = parent.get(path.last().unwrap()) {
+                path.push(prev.clone());
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let len = parent[&name].1;
+        if max_len.is_some_and(|m| len >= m) {
+            continue;
+        }
+        let mut deps: Vec<&str> = graph
+            .dependencies(&name)
+            .filter(|d| allowed.contains(d) && !removed_nodes.contains(d))
+            .filter(|d| !removed_edges.contains(&(name.as_str(), *d)))
+            .collect();
+        deps.sort();
+        for dep in deps {
+            if !parent.contains_key(dep) {
+                parent.insert(dep.to_string(), (Some(name.clone()), len + 1));
+                queue.push_back(dep.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Узел перевёрнутого дерева: цель в корне, далее зависящие от неё пакеты до корня графа.
+/// Узлы хранятся в общем массиве, потомки - индексами в нём
+struct InvertedNode {
+    name: String,
+    children: Vec<usize>,
+}
+
+impl WhyReport {
+    /// Текстовое представление в виде перевёрнутого дерева с подписями рёбер
+    pub fn to_text(&self, graph: &DependencyGraph) -> String {
+        if self.paths.is_empty() {
+            return format!("'{}' is not reachable from '{}'\n", self.target, self.root);
+        }
+
+        let mut tree = vec![InvertedNode { name: self.target.clone(), children: Vec::new() }];
+        for path in &self.paths {
+            let mut node = 0;
+            for name in path.iter().rev().skip(1) {
+                node = match tree[node].children.iter().find(|&&c| &tree[c].name == name) {
+                    Some(&c) => c,
+                    None => {
+                        tree.push(InvertedNode { name: name.clone(), children: Vec::new() });
+                        let c = tree.len() - 1;
+                        tree[node].children.push(c);
+                        c
+                    }
+                };
+            }
+        }
+
+        let mut out = format!(
+            "Why '{}' is in the dependency graph of '{}' ({} path(s)):\n",
+            self.target,
+            self.root,
+            self.paths.len()
+        );
+        write_inverted(&tree, graph, &mut out);
+        if self.truncated {
+            out.push_str(&format!("… (stopped after {} paths)\n", self.paths.len()));
+        }
+        out
+    }
+
+    /// Представление в JSON: пути с подписями рёбер
+    pub fn to_json(&self, graph: &DependencyGraph) -> JsonValue {
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| {
+                let edges = path
+                    .windows(2)
+                    .map(|w| {
+                        let mut fields = vec![("from", w[0].as_str().into()), ("to", w[1].as_str().into())];
+                        if let Some(info) = graph.edge_info(&w[0], &w[1]) {
+                            fields.push(("kind", info.kind.as_str().into()));
+                            fields.push(("optional", info.optional.into()));
+                            fields.push(("features", JsonValue::strings(&info.features)));
+                        }
+                        JsonValue::object(fields)
+                    })
+                    .collect();
+                JsonValue::object(vec![("nodes", JsonValue::strings(path)), ("edges", JsonValue::Array(edges))])
+            })
+            .collect();
+
+        JsonValue::object(vec![
+            ("root", self.root.as_str().into()),
+            ("target", self.target.as_str().into()),
+            ("truncated", self.truncated.into()),
+            ("paths", JsonValue::Array(paths)),
+        ])
+    }
+}
+
+// Вывод перевёрнутого дерева с явным стеком; у каждого узла подписывается ребро
+// к пакету уровнем выше, от которого он зависит
+fn write_inverted(tree: &[InvertedNode], graph: &DependencyGraph, out: &mut String) {
+    let mut stack: Vec<(usize, Option<usize>, usize)> = vec![(0, None, 0)];
+    while let Some((node, child, depth)) = stack.pop() {
+        let name = &tree[node].name;
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(name);
+        if let Some(info) = child.and_then(|c| graph.edge_info(name, &tree[c].name)) {
+            out.push_str(&format!(" [{}]", info.label()));
+        }
+        out.push('\n');
+        stack.extend(tree[node].children.iter().rev().map(|&c| (c, Some(node), depth + 1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> DependencyGraph {
+        let mut g = DependencyGraph::new();
+        for (from, to) in edges {
+            g.add_edge(from, to);
+        }
+        g
+    }
+
+    fn paths(report: &WhyReport) -> Vec<String> {
+        report.paths.iter().map(|p| p.join(" ")).collect()
+    }
+
+    #[test]
+    fn diamond_has_both_paths() {
+        let g = graph(&[("A", "B"), ("A", "C"), ("B", "D"), ("C", "D")]);
+        let report = explain(&g, "A", "D", None, None, 100);
+        assert_eq!(paths(&report), ["A B D", "A C D"]);
+        assert!(!report.truncated);
+        assert_eq!(report.to_text(&g), "Why 'D' is in the dependency graph of 'A' (2 path(s)):\nD\n  B\n    A\n  C\n    A\n");
+    }
+
+    #[test]
+    fn cycle_on_path_keeps_paths_simple() {
+        let g = graph(&[("A", "B"), ("B", "C"), ("C", "B"), ("C", "A"), ("C", "D"), ("B", "D")]);
+        let report = explain(&g, "A", "D", None, None, 100);
+        assert_eq!(paths(&report), ["A B D", "A B C D"]);
+        let report = explain(&g, "A", "D", Some(5), None, 100);
+        assert_eq!(paths(&report), ["A B D", "A B C D"]);
+    }
+
+    #[test]
+    fn max_len_limits_edges() {
+        let g = graph(&[("A", "D"), ("A", "B"), ("B", "D"), ("B", "C"), ("C", "D")]);
+        assert_eq!(paths(&explain(&g, "A", "D", None, Some(2), 100)), ["A D", "A B D"]);
+        assert_eq!(paths(&explain(&g, "A", "D", Some(5), Some(2), 100)), ["A D", "A B D"]);
+        assert!(explain(&g, "A", "D", None, Some(0), 100).paths.is_empty());
+    }
+
+    #[test]
+    fn max_paths_stops_enumeration() {
+        // Цепочка из четырёх ромбов: 2^4 путей
+        let mut g = DependencyGraph::new();
+        for i in 0..4 {
+            let (from, to) = (format!("N{i}"), format!("N{}", i + 1));
+            for side in ["L", "R"] {
+                let mid = format!("{side}{i}");
+                g.add_edge(&from, &mid);
+                g.add_edge(&mid, &to);
+            }
+        }
+        assert_eq!(explain(&g, "N0", "N4", None, None, 100).paths.len(), 16);
+        let report = explain(&g, "N0", "N4", None, None, 5);
+        assert_eq!(report.paths.len(), 5);
+        assert!(report.truncated);
+        assert!(report.to_text(&g).ends_with("… (stopped after 5 paths)\n"));
+    }
+
+    #[test]
+    fn shortest_paths_in_length_order() {
+        let g = graph(&[("A", "B"), ("B", "C"), ("C", "T"), ("A", "X"), ("X", "T"), ("A", "T"), ("B", "T")]);
+        let report = explain(&g, "A", "T", Some(3), None, 100);
+        assert_eq!(paths(&report), ["A T", "A B T", "A X T"]);
+        let report = explain(&g, "A", "T", Some(10), None, 100);
+        assert_eq!(paths(&report), ["A T", "A B T", "A X T", "A B C T"]);
+        assert!(explain(&g, "T", "A", Some(3), None, 100).paths.is_empty());
+    }
+
+    #[test]
+    fn long_chain_does_not_overflow() {
+        let names: Vec<String> = (0..100_000).map(|i| format!("n{i}")).collect();
+        let mut g = DependencyGraph::new();
+        for pair in names.windows(2) {
+            g.add_edge(&pair[0], &pair[1]);
+        }
+        let report = explain(&g, "n0", "n99999", None, None, 10);
+        assert_eq!(report.paths.len(), 1);
+        assert_eq!(report.paths[0].len(), 100_000);
+    }
+}